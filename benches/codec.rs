@@ -0,0 +1,132 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use bipack_ru::bipack_sink::BipackSink;
+use bipack_ru::bipack_source::{BipackError, BipackSource, Result, SliceSource};
+
+/// Source that implements only `get_u8`, so it uses default trait methods: the baseline
+/// to compare [SliceSource] overrides with.
+struct ByteSource<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteSource<'a> {
+    fn from(data: &'a [u8]) -> ByteSource<'a> { ByteSource { data, position: 0 } }
+}
+
+impl BipackSource for ByteSource<'_> {
+    fn get_u8(&mut self) -> Result<u8> {
+        let result = *self.data.get(self.position)
+            .ok_or(BipackError::NoDataError { offset: self.position, needed: 1 })?;
+        self.position += 1;
+        Ok(result)
+    }
+}
+
+/// Sink that implements only `put_u8`, the baseline for bulk writes.
+struct ByteSink(Vec<u8>);
+
+impl BipackSink for ByteSink {
+    fn put_u8(&mut self, data: u8) { self.0.push(data) }
+}
+
+fn get_u64(c: &mut Criterion) {
+    let mut data = Vec::new();
+    for i in 0..10_000u64 { data.put_u64(i.wrapping_mul(0x9E3779B97F4A7C15)); }
+
+    let mut group = c.benchmark_group("get_u64");
+    group.bench_function("default", |b| b.iter(|| {
+        let mut src = ByteSource::from(black_box(&data));
+        let mut sum = 0u64;
+        for _ in 0..10_000 { sum = sum.wrapping_add(src.get_u64().unwrap()); }
+        sum
+    }));
+    group.bench_function("slice_source", |b| b.iter(|| {
+        let mut src = SliceSource::from(black_box(&data));
+        let mut sum = 0u64;
+        for _ in 0..10_000 { sum = sum.wrapping_add(src.get_u64().unwrap()); }
+        sum
+    }));
+    group.finish();
+}
+
+fn get_fixed_bytes(c: &mut Criterion) {
+    let data: Vec<u8> = (0..64 * 1024).map(|i| i as u8).collect();
+
+    let mut group = c.benchmark_group("get_fixed_bytes_1k");
+    group.bench_function("default", |b| b.iter(|| {
+        let mut src = ByteSource::from(black_box(&data));
+        for _ in 0..64 { black_box(src.get_fixed_bytes(1024).unwrap()); }
+    }));
+    group.bench_function("slice_source", |b| b.iter(|| {
+        let mut src = SliceSource::from(black_box(&data));
+        for _ in 0..64 { black_box(src.get_fixed_bytes(1024).unwrap()); }
+    }));
+    group.finish();
+}
+
+fn put_fixed_bytes(c: &mut Criterion) {
+    let data: Vec<u8> = (0..64 * 1024).map(|i| i as u8).collect();
+
+    let mut group = c.benchmark_group("put_fixed_bytes_64k");
+    group.bench_function("default", |b| b.iter(|| {
+        let mut sink = ByteSink(Vec::new());
+        sink.put_fixed_bytes(black_box(&data));
+        sink.0
+    }));
+    group.bench_function("vec", |b| b.iter(|| {
+        let mut sink = Vec::new();
+        sink.put_fixed_bytes(black_box(&data));
+        sink
+    }));
+    group.finish();
+}
+
+fn get_unsigned(c: &mut Criterion) {
+    let mut data = Vec::new();
+    for i in 0..1_000_000u64 { data.put_unsigned(i.wrapping_mul(0x9E3779B97F4A7C15) >> (i % 64)); }
+
+    let mut group = c.benchmark_group("get_unsigned_1m");
+    group.sample_size(20);
+    group.bench_function("slice_source", |b| b.iter(|| {
+        let mut src = SliceSource::from(black_box(&data));
+        let mut sum = 0u64;
+        for _ in 0..1_000_000 { sum = sum.wrapping_add(src.get_unsigned().unwrap()); }
+        sum
+    }));
+    group.finish();
+}
+
+fn put_unsigned(c: &mut Criterion) {
+    let values: Vec<u64> = (0..1_000_000u64)
+        .map(|i| i.wrapping_mul(0x9E3779B97F4A7C15) >> (i % 64)).collect();
+
+    let mut group = c.benchmark_group("put_unsigned_1m");
+    group.sample_size(20);
+    group.bench_function("vec", |b| b.iter(|| {
+        let mut sink = Vec::new();
+        for x in black_box(&values) { sink.put_unsigned(*x); }
+        sink
+    }));
+    group.finish();
+}
+
+fn get_str(c: &mut Criterion) {
+    let text: String = (0..1024 * 1024).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+    let mut data = Vec::new();
+    data.put_str(&text);
+
+    let mut group = c.benchmark_group("get_str_1m");
+    group.bench_function("checked", |b| b.iter(|| {
+        SliceSource::from(black_box(&data)).get_str().unwrap()
+    }));
+    group.bench_function("unchecked", |b| b.iter(|| {
+        // safe: the data are packed from a valid string above
+        unsafe { SliceSource::from(black_box(&data)).get_str_unchecked().unwrap() }
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, get_u64, get_fixed_bytes, put_fixed_bytes, get_unsigned, put_unsigned,
+    get_str);
+criterion_main!(benches);
@@ -0,0 +1,126 @@
+// Copyright 2023 by Sergey S. Chernov.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Result;
+
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::bipack::BiPackable;
+use crate::bipack_sink::{BipackSink, IntoU64};
+
+/// Async bipack sink writing to tokio [AsyncWrite], e.g. a socket. Available with `tokio`
+/// feature. Methods are the same as of [BipackSink], but async and returning write errors.
+///
+/// Data are collected in the internal buffer and written out when it is full, so call
+/// [AsyncWriteSink::flush] when done.
+pub struct AsyncWriteSink<W: AsyncWrite + Unpin> {
+    writer: W,
+    buffer: Vec<u8>,
+    buffer_size: usize,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWriteSink<W> {
+    pub fn new(writer: W) -> AsyncWriteSink<W> {
+        AsyncWriteSink::with_buffer_size(writer, 8192)
+    }
+
+    pub fn with_buffer_size(writer: W, buffer_size: usize) -> AsyncWriteSink<W> {
+        AsyncWriteSink { writer, buffer: Vec::with_capacity(buffer_size), buffer_size }
+    }
+
+    /// Write all buffered data and flush the writer.
+    pub async fn flush(&mut self) -> Result<()> {
+        self.writer.write_all(&self.buffer).await?;
+        self.buffer.clear();
+        self.writer.flush().await
+    }
+
+    /// Return the writer. Buffered data are lost unless [AsyncWriteSink::flush] is
+    /// called first.
+    pub fn into_inner(self) -> W { self.writer }
+
+    async fn write_if_full(&mut self) -> Result<()> {
+        if self.buffer.len() >= self.buffer_size {
+            self.writer.write_all(&self.buffer).await?;
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+
+    pub async fn put_u8(&mut self, data: u8) -> Result<()> {
+        self.buffer.put_u8(data);
+        self.write_if_full().await
+    }
+
+    pub async fn put_fixed_bytes(&mut self, data: &[u8]) -> Result<()> {
+        self.buffer.put_fixed_bytes(data);
+        self.write_if_full().await
+    }
+
+    pub async fn put_var_bytes(&mut self, data: &[u8]) -> Result<()> {
+        self.buffer.put_var_bytes(data);
+        self.write_if_full().await
+    }
+
+    pub async fn put_str(&mut self, str: &str) -> Result<()> {
+        self.buffer.put_str(str);
+        self.write_if_full().await
+    }
+
+    pub async fn put_u16(&mut self, value: u16) -> Result<()> {
+        self.buffer.put_u16(value);
+        self.write_if_full().await
+    }
+
+    pub async fn put_u32(&mut self, value: u32) -> Result<()> {
+        self.buffer.put_u32(value);
+        self.write_if_full().await
+    }
+
+    pub async fn put_u64(&mut self, value: u64) -> Result<()> {
+        self.buffer.put_u64(value);
+        self.write_if_full().await
+    }
+
+    pub async fn put_i64(&mut self, value: i64) -> Result<()> {
+        self.put_u64(value as u64).await
+    }
+    pub async fn put_i32(&mut self, value: i32) -> Result<()> {
+        self.put_u32(value as u32).await
+    }
+    pub async fn put_i16(&mut self, value: i16) -> Result<()> {
+        self.put_u16(value as u16).await
+    }
+    pub async fn put_i8(&mut self, value: i8) -> Result<()> {
+        self.put_u8(value as u8).await
+    }
+
+    /// Put smartint, see [BipackSink::put_unsigned].
+    pub async fn put_unsigned<T: IntoU64>(&mut self, number: T) -> Result<()> {
+        self.buffer.put_unsigned(number);
+        self.write_if_full().await
+    }
+
+    /// Put signed smartint, see [BipackSink::put_signed].
+    pub async fn put_signed(&mut self, value: i64) -> Result<()> {
+        self.buffer.put_signed(value);
+        self.write_if_full().await
+    }
+
+    /// Pack any [BiPackable] value.
+    pub async fn put<T: BiPackable + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.bi_pack(&mut self.buffer);
+        self.write_if_full().await
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright 2023 by Sergey S. Chernov.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::bipack_source::BipackError::NoDataError;
+use crate::bipack_source::{BipackError, Result};
+
+/// Async bipack source reading from tokio [AsyncRead], e.g. a socket. Available with
+/// `tokio` feature. Methods are the same as of [crate::bipack_source::BipackSource], but
+/// async.
+///
+/// Numbers are read byte by byte, so wrap unbuffered readers into
+/// `tokio::io::BufReader`.
+pub struct AsyncReadSource<R: AsyncRead + Unpin> {
+    reader: R,
+    position: usize,
+}
+
+impl<R: AsyncRead + Unpin> AsyncReadSource<R> {
+    pub fn new(reader: R) -> AsyncReadSource<R> {
+        AsyncReadSource { reader, position: 0 }
+    }
+
+    /// Number of bytes read so far.
+    pub fn position(&self) -> usize { self.position }
+
+    pub fn into_inner(self) -> R { self.reader }
+
+    /// Read exactly `buf.len()` bytes, see
+    /// [crate::bipack_source::BipackSource::get_fixed_bytes_into].
+    pub async fn get_fixed_bytes_into(&mut self, buf: &mut [u8]) -> Result<()> {
+        let mut done = 0;
+        while done < buf.len() {
+            match self.reader.read(&mut buf[done..]).await {
+                Ok(0) => return Err(NoDataError { offset: self.position, needed: buf.len() - done }),
+                Ok(n) => {
+                    done += n;
+                    self.position += n;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(BipackError::IoError(e.kind())),
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn get_u8(&mut self) -> Result<u8> {
+        let mut bytes = [0u8; 1];
+        self.get_fixed_bytes_into(&mut bytes).await?;
+        Ok(bytes[0])
+    }
+
+    pub async fn get_u16(&mut self) -> Result<u16> {
+        let mut bytes = [0u8; 2];
+        self.get_fixed_bytes_into(&mut bytes).await?;
+        Ok(u16::from_be_bytes(bytes))
+    }
+
+    pub async fn get_u32(&mut self) -> Result<u32> {
+        let mut bytes = [0u8; 4];
+        self.get_fixed_bytes_into(&mut bytes).await?;
+        Ok(u32::from_be_bytes(bytes))
+    }
+
+    pub async fn get_u64(&mut self) -> Result<u64> {
+        let mut bytes = [0u8; 8];
+        self.get_fixed_bytes_into(&mut bytes).await?;
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    pub async fn get_i64(&mut self) -> Result<i64> {
+        Ok(self.get_u64().await? as i64)
+    }
+    pub async fn get_i32(&mut self) -> Result<i32> {
+        Ok(self.get_u32().await? as i32)
+    }
+    pub async fn get_i16(&mut self) -> Result<i16> {
+        Ok(self.get_u16().await? as i16)
+    }
+    pub async fn get_i8(&mut self) -> Result<i8> {
+        Ok(self.get_u8().await? as i8)
+    }
+
+    /// Unpack smartint, see [crate::bipack_source::BipackSource::get_unsigned].
+    pub async fn get_unsigned(&mut self) -> Result<u64> {
+        let first = self.get_u8().await? as u64;
+        let mut ty = first & 3;
+
+        let mut result = first >> 2;
+        if ty == 0 { return Ok(result); }
+        ty -= 1;
+
+        result += (self.get_u8().await? as u64) << 6;
+        if ty == 0 { return Ok(result); }
+        ty -= 1;
+
+        result += (self.get_u8().await? as u64) << 14;
+        if ty == 0 { return Ok(result); }
+
+        Ok(result | (self.get_varint_unsigned().await? << 22))
+    }
+
+    /// Unpack signed smartint, see [crate::bipack_source::BipackSource::get_signed].
+    pub async fn get_signed(&mut self) -> Result<i64> {
+        let value = self.get_unsigned().await?;
+        let result = (value >> 1) as i64;
+        Ok(if value & 1 != 0 { -result } else { result })
+    }
+
+    /// See [crate::bipack_source::BipackSource::get_varint_unsigned]. Values that do not
+    /// fit `u64` cause [BipackError::Overflow].
+    pub async fn get_varint_unsigned(&mut self) -> Result<u64> {
+        let mut result = 0u64;
+        let mut count = 0;
+        loop {
+            let x = self.get_u8().await? as u64;
+            if count > 63 || (count == 63 && (x & 0x7F) > 1) { return Err(BipackError::Overflow); }
+            result |= (x & 0x7F) << count;
+            if (x & 0x80) == 0 { return Ok(result); }
+            count += 7
+        }
+    }
+
+    /// Read exact number of bytes. The buffer grows as data arrive, so a wrong size does
+    /// not cause a huge allocation.
+    pub async fn get_fixed_bytes(&mut self, size: usize) -> Result<Vec<u8>> {
+        let mut result = Vec::new();
+        while result.len() < size {
+            let start = result.len();
+            result.resize(start + (size - start).min(4096), 0);
+            self.get_fixed_bytes_into(&mut result[start..]).await?;
+        }
+        Ok(result)
+    }
+
+    /// See [crate::bipack_source::BipackSource::get_var_bytes].
+    pub async fn get_var_bytes(&mut self) -> Result<Vec<u8>> {
+        let size = self.get_unsigned().await? as usize;
+        self.get_fixed_bytes(size).await
+    }
+
+    /// See [crate::bipack_source::BipackSource::get_str].
+    pub async fn get_str(&mut self) -> Result<String> {
+        String::from_utf8(self.get_var_bytes().await?).map_err(BipackError::BadEncoding)
+    }
+}
@@ -1,17 +1,66 @@
-use crate::bipack_sink::{BipackSink, IntoU64};
-use crate::bipack_source::{BipackSource, Result};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::num::{NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize};
+use crate::bipack_sink::BipackSink;
+use crate::bipack_source::{BipackError, BipackSource, Result, SliceSource};
 
 /// The trait to unpack to be used in serializer to come. Please don't use it, it is
 /// experimental.
 pub trait BiPackable {
     fn bi_pack(self: &Self, sink: &mut impl BipackSink);
+
+    /// Pack the value as an element of an array, vector or sequence. Override it along with
+    /// [BiPackable::bi_pack_slice] for types packed differently in bulk, like `u8`.
+    fn bi_pack_element(self: &Self, sink: &mut impl BipackSink) {
+        self.bi_pack(sink)
+    }
+
+    /// Pack consecutive values, used to pack arrays. Override it for types that could
+    /// be packed in bulk, like `u8`.
+    fn bi_pack_slice(items: &[Self], sink: &mut impl BipackSink) where Self: Sized {
+        for x in items { x.bi_pack_element(sink); }
+    }
 }
 
-/// The trait need by [bipack()] macro and in the serializer to come, packs some
+/// The trait need by [`bipack!`](crate::bipack!) macro and in the serializer to come, packs some
 /// type into a generic sink.
+///
+/// The source is generic rather than `dyn` so that reading primitives could be inlined.
 pub trait BiUnpackable where Self: Sized {
 
-    fn bi_unpack(source: &mut dyn BipackSource) -> Result<Self>;
+    fn bi_unpack(source: &mut impl BipackSource) -> Result<Self>;
+
+    /// Unpack an element packed with [BiPackable::bi_pack_element].
+    fn bi_unpack_element(source: &mut impl BipackSource) -> Result<Self> {
+        Self::bi_unpack(source)
+    }
+
+    /// Unpack `N` consecutive values, used to unpack arrays. Override it for types that
+    /// could be unpacked in bulk, like `u8`.
+    fn bi_unpack_array<const N: usize>(source: &mut impl BipackSource) -> Result<[Self; N]> {
+        let mut result = Vec::with_capacity(N);
+        for i in 0..N { result.push(Self::bi_unpack_element(source)?); }
+        Ok(result.try_into().unwrap_or_else(|_| unreachable!()))
+    }
+
+    /// Unpack `size` consecutive values, used to unpack vectors. Override it for types that
+    /// could be unpacked in bulk, like `u8`.
+    fn bi_unpack_vec(size: usize, source: &mut impl BipackSource) -> Result<Vec<Self>> {
+        // size comes from the data, so no preallocation
+        let mut result = Vec::new();
+        for i in 0..size { result.push(Self::bi_unpack_element(source)?); }
+        Ok(result)
+    }
+}
+
+/// Unpack a value that must occupy the whole `data`, otherwise fail with
+/// [BipackError::TrailingData], see [SliceSource::finish].
+pub fn from_slice_exact<T: BiUnpackable>(data: &[u8]) -> Result<T> {
+    let mut source = SliceSource::from(data);
+    let result = T::bi_unpack(&mut source)?;
+    source.finish()?;
+    Ok(result)
 }
 
 /// Pack all arguments according to their type, using variable-length
@@ -30,29 +79,141 @@ macro_rules! bipack {
     }};
 }
 
-impl<T: IntoU64 + Copy> BiPackable for T {
+/// Pack all arguments like [crate::bipack!] does, but append them to an existing sink given as
+/// the first argument (`&mut impl BipackSink`), instead of allocating a new `Vec<u8>`.
+#[macro_export]
+macro_rules! bipack_into {
+    ( $sink: expr $(, $e: expr)* ) => {{
+        let sink = $sink;
+        $(
+            $e.bi_pack(&mut *sink);
+        )*
+    }};
+}
+
+/// Simple (fieldless) enums packed by their tag, usually the `repr` discriminant, as
+/// smartint. Implement it and use [crate::bipack_enum!] to make the enum [BiPackable] and
+/// [BiUnpackable].
+pub trait EnumCodec: Sized {
+    fn to_tag(self: &Self) -> u64;
+
+    /// Return the variant for the tag or [BipackError::UnknownVariant].
+    fn from_tag(tag: u64) -> Result<Self>;
+}
+
+/// Implement [BiPackable] and [BiUnpackable] for enums implementing [EnumCodec].
+#[macro_export]
+macro_rules! bipack_enum {
+    ( $($type: ty),* ) => {
+        $(impl $crate::bipack::BiPackable for $type {
+            fn bi_pack(self: &Self, sink: &mut impl $crate::bipack_sink::BipackSink) {
+                sink.put_unsigned($crate::bipack::EnumCodec::to_tag(self))
+            }
+        }
+
+        impl $crate::bipack::BiUnpackable for $type {
+            fn bi_unpack(source: &mut impl $crate::bipack_source::BipackSource)
+                -> $crate::bipack_source::Result<$type> {
+                <$type as $crate::bipack::EnumCodec>::from_tag(source.get_unsigned()?)
+            }
+        })*
+    };
+}
+
+/// Implement [BiPackable] for types implementing [crate::bipack_sink::IntoU64], packing
+/// them as smartints. Unsigned integer types already have it, use it for own types like
+/// id wrappers.
+#[macro_export]
+macro_rules! bipack_unsigned {
+    ( $($type: ty),* ) => {
+        $(impl $crate::bipack::BiPackable for $type {
+            fn bi_pack(self: &Self, sink: &mut impl $crate::bipack_sink::BipackSink) {
+                sink.put_unsigned($crate::bipack_sink::IntoU64::into_u64(*self))
+            }
+        })*
+    };
+}
+
+bipack_unsigned!(u16, u32, u64, usize, NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroUsize);
+
+/// Scalar `u8` is a smartint like other integers, but arrays and vectors of it are packed
+/// as plain bytes.
+impl BiPackable for u8 {
     fn bi_pack(self: &Self, sink: &mut impl BipackSink) {
-        sink.put_unsigned(self.into_u64())
+        sink.put_unsigned(*self)
+    }
+
+    fn bi_pack_element(self: &Self, sink: &mut impl BipackSink) {
+        sink.put_u8(*self)
+    }
+
+    fn bi_pack_slice(items: &[u8], sink: &mut impl BipackSink) {
+        sink.put_fixed_bytes(items)
     }
 }
 
-impl BiPackable for &str {
+impl BiPackable for str {
     fn bi_pack(self: &Self, sink: &mut impl BipackSink) {
         sink.put_str(self)
     }
 }
 
+impl<T: BiPackable + ?Sized> BiPackable for &T {
+    fn bi_pack(self: &Self, sink: &mut impl BipackSink) {
+        (**self).bi_pack(sink)
+    }
+
+    fn bi_pack_element(self: &Self, sink: &mut impl BipackSink) {
+        (**self).bi_pack_element(sink)
+    }
+}
+
+impl<T: BiPackable + ?Sized> BiPackable for Box<T> {
+    fn bi_pack(self: &Self, sink: &mut impl BipackSink) {
+        (**self).bi_pack(sink)
+    }
+
+    fn bi_pack_element(self: &Self, sink: &mut impl BipackSink) {
+        (**self).bi_pack_element(sink)
+    }
+}
+
+impl<T: BiUnpackable> BiUnpackable for Box<T> {
+    fn bi_unpack(source: &mut impl BipackSource) -> Result<Box<T>> {
+        Ok(Box::new(T::bi_unpack(source)?))
+    }
+
+    fn bi_unpack_element(source: &mut impl BipackSource) -> Result<Box<T>> {
+        Ok(Box::new(T::bi_unpack_element(source)?))
+    }
+}
+
 macro_rules! declare_unpack_u {
     ($($type:ident),*) => {
         $(impl BiUnpackable for $type {
-            fn bi_unpack(source: &mut dyn BipackSource) -> Result<$type> {
-                Ok(source.get_unsigned()? as $type)
+            fn bi_unpack(source: &mut impl BipackSource) -> Result<$type> {
+                source.get_unsigned_as()
             }
         })*
     };
 }
 
-declare_unpack_u!(u16, u32, u64);
+declare_unpack_u!(u16, u32, u64, usize);
+
+/// Non-zero integers are packed as smartints, including `NonZeroU8`. Zero on unpacking is
+/// [BipackError::InvalidValue].
+macro_rules! declare_unpack_nonzero {
+    ($($type:ident: $inner:ident),*) => {
+        $(impl BiUnpackable for $type {
+            fn bi_unpack(source: &mut impl BipackSource) -> Result<$type> {
+                $type::new(source.get_unsigned_as::<$inner>()?).ok_or(BipackError::InvalidValue)
+            }
+        })*
+    };
+}
+
+declare_unpack_nonzero!(NonZeroU8: u8, NonZeroU16: u16, NonZeroU32: u32, NonZeroU64: u64,
+    NonZeroUsize: usize);
 
 // impl<String> BiUnpackable<String> for String {
 //     fn bi_unpack(source: &mut impl BipackSource) -> Result<Self> {
@@ -64,16 +225,206 @@ declare_unpack_u!(u16, u32, u64);
 //
 // }
 
+/// Enums with data (tagged unions), packed as the smartint tag followed by the variant body.
+/// Use [pack_with_header] to hoist fields shared by all variants, like a timestamp, before
+/// the tag.
+pub trait VariantCodec: Sized {
+    fn tag(self: &Self) -> u64;
+
+    /// Pack the variant fields, without the tag.
+    fn pack_body(self: &Self, sink: &mut impl BipackSink);
+
+    /// Unpack the body of the variant with `tag` or fail with
+    /// [crate::bipack_source::BipackError::UnknownVariant].
+    fn unpack_body(tag: u64, source: &mut impl BipackSource) -> Result<Self>;
+}
+
+/// Pack the tagged union with the common header: `header` first, then the tag and body of
+/// `value`, as in protocols with a common header and variant-specific body. Readers
+/// decode the header uniformly regardless of variant, even the ones they don't know, see
+/// [unpack_with_header].
+pub fn pack_with_header<H: BiPackable, V: VariantCodec>(header: &H, value: &V, sink: &mut impl BipackSink) {
+    header.bi_pack(sink);
+    sink.put_unsigned(value.tag());
+    value.pack_body(sink);
+}
+
+/// Unpack data packed with [pack_with_header].
+pub fn unpack_with_header<H: BiUnpackable, V: VariantCodec>(source: &mut impl BipackSource) -> Result<(H, V)> {
+    let header = H::bi_unpack(source)?;
+    let tag = source.get_unsigned()?;
+    Ok((header, V::unpack_body(tag, source)?))
+}
+
+/// Scalar `u8` is a smartint, like it is packed; arrays and vectors of it are plain bytes.
 impl BiUnpackable for u8 {
-    fn bi_unpack(source: &mut dyn BipackSource) -> Result<u8> {
+    fn bi_unpack(source: &mut impl BipackSource) -> Result<u8> {
+        source.get_unsigned_as()
+    }
+
+    fn bi_unpack_element(source: &mut impl BipackSource) -> Result<u8> {
         source.get_u8()
     }
+
+    fn bi_unpack_array<const N: usize>(source: &mut impl BipackSource) -> Result<[u8; N]> {
+        let mut result = [0u8; N];
+        source.get_fixed_bytes_into(&mut result)?;
+        Ok(result)
+    }
+
+    fn bi_unpack_vec(size: usize, source: &mut impl BipackSource) -> Result<Vec<u8>> {
+        source.get_fixed_bytes(size)
+    }
+}
+
+/// Arrays are packed without size as it is known statically.
+impl<T: BiPackable, const N: usize> BiPackable for [T; N] {
+    fn bi_pack(self: &Self, sink: &mut impl BipackSink) {
+        T::bi_pack_slice(self, sink)
+    }
+}
+
+impl<T: BiUnpackable, const N: usize> BiUnpackable for [T; N] {
+    fn bi_unpack(source: &mut impl BipackSource) -> Result<[T; N]> {
+        T::bi_unpack_array(source)
+    }
+}
+
+impl BiPackable for String {
+    fn bi_pack(self: &Self, sink: &mut impl BipackSink) {
+        sink.put_str(self)
+    }
 }
 
 impl BiUnpackable for String {
-    fn bi_unpack(source: &mut dyn BipackSource) -> Result<String> {
+    fn bi_unpack(source: &mut impl BipackSource) -> Result<String> {
         source.get_str()
     }
 }
 
+/// Result is packed as a tag byte, 0 for `Ok` and 1 for `Err`, followed by the value.
+impl<T: BiPackable, E: BiPackable> BiPackable for core::result::Result<T, E> {
+    fn bi_pack(self: &Self, sink: &mut impl BipackSink) {
+        match self {
+            Ok(value) => {
+                sink.put_u8(0);
+                value.bi_pack(sink);
+            }
+            Err(error) => {
+                sink.put_u8(1);
+                error.bi_pack(sink);
+            }
+        }
+    }
+}
+
+/// Unknown tag causes [BipackError::UnknownVariant].
+impl<T: BiUnpackable, E: BiUnpackable> BiUnpackable for core::result::Result<T, E> {
+    fn bi_unpack(source: &mut impl BipackSource) -> Result<core::result::Result<T, E>> {
+        match source.get_u8()? {
+            0 => Ok(Ok(T::bi_unpack(source)?)),
+            1 => Ok(Err(E::bi_unpack(source)?)),
+            tag => Err(BipackError::UnknownVariant(tag as u64)),
+        }
+    }
+}
+
+/// Vectors are packed as smartint size followed by elements, so `Vec<u8>` is the same as
+/// [BipackSink::put_var_bytes].
+impl<T: BiPackable> BiPackable for Vec<T> {
+    fn bi_pack(self: &Self, sink: &mut impl BipackSink) {
+        sink.put_unsigned(self.len());
+        T::bi_pack_slice(self, sink)
+    }
+}
+
+impl<T: BiUnpackable> BiUnpackable for Vec<T> {
+    fn bi_unpack(source: &mut impl BipackSource) -> Result<Vec<T>> {
+        let size = source.get_unsigned_as::<usize>()?;
+        T::bi_unpack_vec(size, source)
+    }
+}
+
+/// Option is packed as a tag byte, 0 for `None` and 1 for `Some` followed by the value.
+impl<T: BiPackable> BiPackable for Option<T> {
+    fn bi_pack(self: &Self, sink: &mut impl BipackSink) {
+        match self {
+            None => sink.put_u8(0),
+            Some(value) => {
+                sink.put_u8(1);
+                value.bi_pack(sink);
+            }
+        }
+    }
+}
+
+/// Tag other than 0 or 1 causes [BipackError::InvalidValue].
+impl<T: BiUnpackable> BiUnpackable for Option<T> {
+    fn bi_unpack(source: &mut impl BipackSource) -> Result<Option<T>> {
+        match source.get_u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(T::bi_unpack(source)?)),
+            _ => Err(BipackError::InvalidValue),
+        }
+    }
+}
+
+/// Enum variant packed with [BipackSink::put_variant_body] as is: the tag and the raw
+/// body bytes. Use it for the "unknown" variant of forward-compatible enums: packing it
+/// back produces exactly the same bytes, so data of newer variants are not lost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawVariant {
+    pub tag: u64,
+    pub body: Vec<u8>,
+}
+
+impl BiPackable for RawVariant {
+    fn bi_pack(self: &Self, sink: &mut impl BipackSink) {
+        sink.put_variant_body(self.tag, |b| b.extend_from_slice(&self.body))
+    }
+}
+
+impl BiUnpackable for RawVariant {
+    fn bi_unpack(source: &mut impl BipackSource) -> Result<RawVariant> {
+        let (tag, body) = source.get_variant_body()?;
+        Ok(RawVariant { tag, body })
+    }
+}
+
+/// Values that can be packed as a difference against the previous value of the same type,
+/// to compress series of similar records. A typical implementation packs a bit mask of
+/// changed fields followed by the changed fields only. See [pack_delta_chain].
+pub trait BiDelta: BiPackable + BiUnpackable {
+    fn bi_pack_delta(self: &Self, previous: &Self, sink: &mut impl BipackSink);
+
+    fn bi_unpack_delta(previous: &Self, source: &mut impl BipackSource) -> Result<Self>;
+}
+
+/// Pack records count, then the first record in full and each next one as a delta against
+/// the previous. Use [unpack_delta_chain] to unpack.
+pub fn pack_delta_chain<T: BiDelta>(records: &[T], sink: &mut impl BipackSink) {
+    sink.put_unsigned(records.len());
+    let mut previous: Option<&T> = None;
+    for r in records {
+        match previous {
+            None => r.bi_pack(sink),
+            Some(p) => r.bi_pack_delta(p, sink),
+        }
+        previous = Some(r);
+    }
+}
+
+/// Unpack records packed with [pack_delta_chain], applying deltas cumulatively.
+pub fn unpack_delta_chain<T: BiDelta>(source: &mut impl BipackSource) -> Result<Vec<T>> {
+    let count = source.get_unsigned()?;
+    let mut result: Vec<T> = Vec::new();
+    for i in 0..count {
+        let next = match result.last() {
+            None => T::bi_unpack(source)?,
+            Some(p) => T::bi_unpack_delta(p, source)?,
+        };
+        result.push(next);
+    }
+    Ok(result)
+}
 
@@ -12,12 +12,41 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::iter::Iterator;
-use std::usize;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use core::ops::Deref;
+use core::time::Duration;
 
-const V0LIMIT: u64 = 1u64 << 6;
-const V1LIMIT: u64 = 1u64 << 14;
-const V2LIMIT: u64 = 1u64 << 22;
+use crate::bipack::BiPackable;
+use crate::bipack_source::{BipackError, Result};
+use crate::tools::{crc32, zigzag_encode};
+
+/// Smartint values below this limit are packed in 1 byte (type 0).
+pub const V0LIMIT: u64 = 1u64 << 6;
+/// Smartint values below this limit and not below [V0LIMIT] are packed in 2 bytes (type 1).
+pub const V1LIMIT: u64 = 1u64 << 14;
+/// Smartint values below this limit and not below [V1LIMIT] are packed in 3 bytes (type 2),
+/// bigger values take 4 or more bytes (type 3).
+pub const V2LIMIT: u64 = 1u64 << 22;
+
+/// Alignment of records written by [VecSink::put_aligned_record].
+pub const RECORD_ALIGNMENT: usize = 8;
+
+/// The value passed to [BipackSink::put_unsigned_checked] is negative (or otherwise does
+/// not fit `u64`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct NegativeValue;
+
+impl Display for NegativeValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "value can't be packed as unsigned")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NegativeValue {}
 
 /// Numeric value convertible to Unsigned 64 bit to be used
 /// with [BipackSink#put_unsigned] compressed format. It is implemented fir usize
@@ -38,57 +67,167 @@ macro_rules! into_u64 {
 
 into_u64!(u8, u16, u32, usize, u64);
 
+macro_rules! into_u64_nonzero {
+    ($($type:ident),*) => {
+        $(impl IntoU64 for core::num::$type {
+            fn into_u64(self) -> u64 {
+                self.get() as u64
+            }
+        })*
+    };
+}
+
+into_u64_nonzero!(NonZeroU8, NonZeroU16, NonZeroU32, NonZeroUsize, NonZeroU64);
+
+/// Borrowed values, e.g. struct fields, could be packed as well.
+impl<T: IntoU64 + Copy> IntoU64 for &T {
+    fn into_u64(self) -> u64 {
+        (*self).into_u64()
+    }
+}
+
 /// Data sink to encode bipack binary format.
 ///
-/// To implement just override [BipackSink::put_u8] and optionally [BipackSink::put_fixed_bytes].
+/// To implement just override [BipackSink::put_u8] and optionally [BipackSink::put_slice]
+/// for bulk writes.
 ///
 /// Note that the sink is not returning errors, unlike [crate::bipack_source::BipackSource].
 /// It is supposed that the sink has unlimited
 /// size for the context of data it is used for. This is practical. For the case of overflow-aware
 /// sink you can create one that ignores extra data when overflow is detected and report it
 /// somehow, but for encoding it does not worth effort (data size could be estimated in advance).
+/// Where overflow must be reported, use [crate::try_sink::TryBipackSink] instead.
 pub trait BipackSink {
     fn put_u8(self: &mut Self, data: u8);
 
-    fn put_fixed_bytes(self: &mut Self, data: &[u8]) {
+    /// Write bytes in bulk. The default implementation calls [BipackSink::put_u8] for
+    /// each byte; override it if the sink can do it more effectively. All binaries and
+    /// strings are written with it.
+    fn put_slice(self: &mut Self, data: &[u8]) {
         for b in data { self.put_u8(*b); }
     }
 
+    /// Put bytes as is, without size. It writes with [BipackSink::put_slice], so sinks
+    /// override that to get bulk writes here and in all binaries and strings, as
+    /// `Vec<u8>` does with `extend_from_slice`.
+    fn put_fixed_bytes(self: &mut Self, data: &[u8]) {
+        self.put_slice(data);
+    }
+
+    /// Put signed bytes as is, without size, like [BipackSink::put_fixed_bytes] does.
+    fn put_fixed_i8(self: &mut Self, data: &[i8]) {
+        // SAFETY: i8 and u8 have the same size and alignment, and any bits are valid u8
+        let bytes = unsafe { core::slice::from_raw_parts(data.as_ptr() as *const u8, data.len()) };
+        self.put_fixed_bytes(bytes);
+    }
+
+    /// Put fixed-width field like a hash or a key, the size is checked at compile time.
+    /// Read it with [crate::bipack_source::BipackSource::get_exact].
+    fn put_exact<const N: usize>(self: &mut Self, data: &[u8; N]) {
+        self.put_slice(data);
+    }
+
     fn put_var_bytes(self: &mut Self, data: &[u8]) {
         self.put_unsigned(data.len());
         self.put_fixed_bytes(data);
     }
 
+    /// Put binary as a flag byte and [BipackSink::put_var_bytes] of either the data as is
+    /// (flag 0) or its deflated form (flag 1), whichever is shorter, so small or random data
+    /// cost only the flag byte more. Use
+    /// [crate::bipack_source::BipackSource::get_var_bytes_compressed] to unpack.
+    #[cfg(feature = "flate2")]
+    fn put_var_bytes_compressed(self: &mut Self, data: &[u8]) {
+        use std::io::Write;
+        let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).expect("writing to Vec can't fail");
+        let deflated = encoder.finish().expect("writing to Vec can't fail");
+        if deflated.len() < data.len() {
+            self.put_u8(1);
+            self.put_var_bytes(&deflated);
+        } else {
+            self.put_u8(0);
+            self.put_var_bytes(data);
+        }
+    }
+
     fn put_str(self: &mut Self, str: &str) {
         self.put_var_bytes(str.as_bytes());
     }
 
+    /// Put string as UTF-16 for legacy peers: smartint number of UTF-16 code units, then
+    /// each unit as little-endian u16. Not compatible with [BipackSink::put_str].
+    fn put_str_utf16(self: &mut Self, str: &str) {
+        self.put_unsigned(str.encode_utf16().count());
+        for unit in str.encode_utf16() { self.put_u16_le(unit); }
+    }
+
+    /// Put optional string distinguishing `None` from `Some("")`: the size is packed as
+    /// smartint `0` for `None` and `len + 1` otherwise, so both still take a single byte.
+    /// Read it with [crate::bipack_source::BipackSource::get_opt_str].
+    fn put_opt_str(self: &mut Self, str: Option<&str>) {
+        match str {
+            None => self.put_u8(0),
+            Some(s) => {
+                self.put_unsigned(s.len() as u64 + 1);
+                self.put_fixed_bytes(s.as_bytes());
+            }
+        }
+    }
+
+    /// Put fixed size u16 in big-endian (network) byte order, as all fixed size integers
+    /// are packed unless `_le` methods are used. Read it with
+    /// [crate::bipack_source::BipackSource::get_u16].
     fn put_u16(self: &mut Self, mut value: u16) {
         let mut result = [0u8; 2];
         for i in (0..result.len()).rev() {
             result[i] = value as u8;
-            value = value >> 8;
+            value >>= 8;
         }
         self.put_fixed_bytes(&result);
     }
 
+    /// Put 24-bit big-endian unsigned in 3 bytes, as some media formats use. Panics if
+    /// the value does not fit 24 bits, rather than silently dropping the high byte.
+    fn put_u24(self: &mut Self, value: u32) {
+        assert!(value < 1 << 24, "value {} does not fit 24 bits", value);
+        self.put_fixed_bytes(&value.to_be_bytes()[1..]);
+    }
+
+    /// Put fixed size u32 in big-endian byte order, see [BipackSink::put_u16].
     fn put_u32(self: &mut Self, mut value: u32) {
         let mut result = [0u8; 4];
         for i in (0..result.len()).rev() {
             result[i] = value as u8;
-            value = value >> 8;
+            value >>= 8;
         }
         self.put_fixed_bytes(&result);
     }
+    /// Put fixed size u64 in big-endian byte order, see [BipackSink::put_u16].
     fn put_u64(self: &mut Self, mut value: u64) {
         let mut result = [0u8; 8];
         for i in (0..result.len()).rev() {
             result[i] = value as u8;
-            value = value >> 8;
+            value >>= 8;
         }
         self.put_fixed_bytes(&result);
     }
 
+    /// Put fixed size u16 in little-endian byte order, for interop with systems that use it.
+    /// Note that everything else in bipack, and smartints in particular, has its own byte
+    /// order not affected by these methods.
+    fn put_u16_le(self: &mut Self, value: u16) {
+        self.put_fixed_bytes(&value.to_le_bytes());
+    }
+    /// Put fixed size u32 in little-endian byte order, see [BipackSink::put_u16_le].
+    fn put_u32_le(self: &mut Self, value: u32) {
+        self.put_fixed_bytes(&value.to_le_bytes());
+    }
+    /// Put fixed size u64 in little-endian byte order, see [BipackSink::put_u16_le].
+    fn put_u64_le(self: &mut Self, value: u64) {
+        self.put_fixed_bytes(&value.to_le_bytes());
+    }
+
     fn put_i64(self: &mut Self, value: i64) {
         self.put_u64(value as u64)
     }
@@ -102,51 +241,318 @@ pub trait BipackSink {
         self.put_u8(value as u8)
     }
 
+    /// Put IEEE 754 single precision float as its fixed 4 bytes, big-endian like other
+    /// fixed size values.
+    fn put_f32(self: &mut Self, value: f32) {
+        self.put_u32(value.to_bits())
+    }
+    /// Put IEEE 754 double precision float as its fixed 8 bytes, big-endian.
+    fn put_f64(self: &mut Self, value: f64) {
+        self.put_u64(value.to_bits())
+    }
+
+    /// Put duration as whole seconds and subsecond nanoseconds, both as smartints.
+    fn put_duration(self: &mut Self, value: Duration) {
+        self.put_unsigned(value.as_secs());
+        self.put_unsigned(value.subsec_nanos());
+    }
+
+    /// Put a signed difference between two moments, e.g. of `std::time::Instant`s, in
+    /// nanoseconds. It is not an absolute time. Packed as zig-zag smartint (`0, -1, 1, -2, ...`
+    /// map to `0, 1, 2, 3, ...`), so small deltas of either sign take few bytes.
+    fn put_nanos_delta(self: &mut Self, nanos: i64) {
+        self.put_unsigned(zigzag_encode(nanos));
+    }
+
+    /// Put exact decimal number `mantissa * 10^-scale`, e.g. money amount, without float
+    /// rounding. Mantissa is packed as zig-zag smartint, like [BipackSink::put_nanos_delta],
+    /// followed by the scale byte.
+    fn put_fixed_point(self: &mut Self, mantissa: i64, scale: u8) {
+        self.put_unsigned(zigzag_encode(mantissa));
+        self.put_u8(scale);
+    }
+
+    /// Put time as duration since the Unix epoch, see [BipackSink::put_duration]. Times
+    /// before the epoch can't be packed this way and cause [BipackError::InvalidValue],
+    /// nothing is written then.
+    #[cfg(feature = "std")]
+    fn put_system_time(self: &mut Self, value: std::time::SystemTime) -> Result<()> {
+        let since_epoch = value.duration_since(std::time::UNIX_EPOCH)
+            .map_err(|_| BipackError::InvalidValue)?;
+        self.put_duration(since_epoch);
+        Ok(())
+    }
+
+    /// Put IP address as a tag byte, 4 for IPv4 and 6 for IPv6, followed by 4 or 16
+    /// address bytes.
+    #[cfg(feature = "std")]
+    fn put_ip_addr(self: &mut Self, addr: std::net::IpAddr) {
+        match addr {
+            std::net::IpAddr::V4(a) => {
+                self.put_u8(4);
+                self.put_fixed_bytes(&a.octets());
+            }
+            std::net::IpAddr::V6(a) => {
+                self.put_u8(6);
+                self.put_fixed_bytes(&a.octets());
+            }
+        }
+    }
+
+    /// Put socket address as [BipackSink::put_ip_addr] followed by the port as smartint.
+    /// IPv6 flow info and scope id are not packed.
+    #[cfg(feature = "std")]
+    fn put_socket_addr(self: &mut Self, addr: std::net::SocketAddr) {
+        self.put_ip_addr(addr.ip());
+        self.put_unsigned(addr.port());
+    }
+
+    /// Put a row-major matrix of floats: `rows` and `cols` as smartints and then all the
+    /// elements with [BipackSink::put_f32]. Nothing is written and it fails with
+    /// [BipackError::Overflow] if `rows * cols` does not fit `usize`, or with
+    /// [BipackError::InvalidValue] if `data.len()` is not `rows * cols`.
+    fn put_f32_matrix(self: &mut Self, rows: usize, cols: usize, data: &[f32]) -> Result<()> {
+        if rows.checked_mul(cols).ok_or(BipackError::Overflow)? != data.len() {
+            return Err(BipackError::InvalidValue);
+        }
+        self.put_unsigned(rows);
+        self.put_unsigned(cols);
+        for x in data { self.put_f32(*x); }
+        Ok(())
+    }
+
+    /// Put integers array as the count followed by each value as smartint, so arrays of
+    /// mostly small values take much less than with fixed size integers. Use
+    /// [crate::bipack_source::BipackSource::get_unsigned_vec] to unpack.
+    fn put_unsigned_slice<T: IntoU64 + Copy>(self: &mut Self, data: &[T]) {
+        self.put_unsigned(data.len());
+        for x in data { self.put_unsigned(*x); }
+    }
+
+    /// Put non-decreasing integers, like sorted ids, as the count, the first value and then
+    /// differences between consecutive values, all as smartints, so clustered values take
+    /// a byte or two each. Panics if the data are not sorted. Use
+    /// [crate::bipack_source::BipackSource::get_sorted_unsigned] to unpack.
+    fn put_sorted_unsigned(self: &mut Self, sorted: &[u64]) {
+        self.put_unsigned(sorted.len());
+        let mut last = 0u64;
+        for x in sorted {
+            assert!(*x >= last, "values are not sorted: {} goes after {}", x, last);
+            self.put_unsigned(x - last);
+            last = *x;
+        }
+    }
+
+    /// Put signed (or any other) value known to be non-negative as smartint like
+    /// [BipackSink::put_unsigned]. Negative values are rejected with [NegativeValue] and
+    /// nothing is written, instead of wrapping into a huge unsigned.
+    fn put_unsigned_checked<T: TryInto<u64>>(self: &mut Self, value: T)
+        -> core::result::Result<(), NegativeValue> {
+        self.put_unsigned(value.try_into().map_err(|_| NegativeValue)?);
+        Ok(())
+    }
+
     /// Put unsigned value to compressed variable-length format, `Smartint` in the bipack
     /// terms. This format is used to store size of variable-length binaries and strings.
     /// Use [crate::bipack_source::BipackSource::get_unsigned] to unpack it.
     fn put_unsigned<T: IntoU64>(self: &mut Self, number: T) {
         let value = number.into_u64();
-        let mut encode_seq = |ty: u8, bytes: &[u64]| {
-            if bytes.len() == 0 { self.put_u8(0); } else {
-                if bytes[0] as u64 > V0LIMIT { panic!("first byte is too big (internal error)"); }
-                self.put_u8((ty & 0x03) | ((bytes[0] as u8) << 2));
-                for i in 1..bytes.len() {
-                    self.put_u8(bytes[i] as u8);
-                }
-            }
-        };
-
+        // type in bits 0..1, then lowest 6 bits of the value
+        let first = ((value & 0x3F) as u8) << 2;
         if value < V0LIMIT {
-            encode_seq(0, &[value]);
+            self.put_u8(first);
         } else if value < V1LIMIT {
-            encode_seq(1, &[value & 0x3F, value >> 6]);
+            self.put_slice(&[first | 1, (value >> 6) as u8]);
         } else if value < V2LIMIT {
-            encode_seq(2, &[value & 0x3f, value >> 6, value >> 14]);
+            self.put_slice(&[first | 2, (value >> 6) as u8, (value >> 14) as u8]);
         } else {
-            encode_seq(3, &[value & 0x3f, value >> 6, value >> 14]);
+            self.put_slice(&[first | 3, (value >> 6) as u8, (value >> 14) as u8]);
             self.put_var_unsigned(value >> 22);
         }
     }
 
+    /// Put `u128` as smartint extended to 128 bits: values that fit `u64` are packed exactly
+    /// as [BipackSink::put_unsigned] does, bigger ones are type 3 with the lowest 22 bits in
+    /// v0..v2 and the rest (up to 106 bits) in the varint tail, which is just longer, up to
+    /// 16 bytes, so the whole value takes at most 19 bytes. Use
+    /// [crate::bipack_source::BipackSource::get_unsigned_u128] to unpack it.
+    fn put_unsigned_u128(self: &mut Self, value: u128) {
+        if let Ok(small) = u64::try_from(value) { return self.put_unsigned(small); }
+        self.put_slice(&[((value & 0x3F) as u8) << 2 | 3, (value >> 6) as u8, (value >> 14) as u8]);
+        let mut rest = value >> 22;
+        while rest >= 0x80 {
+            self.put_u8((rest as u8 & 0x7F) | 0x80);
+            rest >>= 7;
+        }
+        self.put_u8(rest as u8);
+    }
+
     /// Put variable-length encoded integer value. it is packed just like variable-length
     /// unsigned value except that LSB (bit 0) is used as negative number flag (when set,
     /// the encoded number is negative).
     ///
-    /// Note that because of this the range of supported integers is one bit smaller than
-    /// i64, only 30 bits for value and one for a sign. This will probably be fixed later
-    /// but please note that it is impractical to store really big numbers in variable-length
-    /// format, consider using [BipackSink::put_i64] instead, it has no such limitation.
+    /// Note that because of this the magnitude can't exceed `i64::MAX`, so `i64::MIN` can't
+    /// be packed: it is written as negative zero and is read back as 0. Use
+    /// [BipackSink::put_signed_checked] to reject it. Please note that it is impractical to
+    /// store really big numbers in variable-length format, consider using
+    /// [BipackSink::put_i64] instead, it has no such limitation.
     fn put_signed(self: &mut Self, val: i64) {
-        let (neg, val) = if val < 0 { (1, -val) } else { (0, val) };
+        let (neg, val) = if val < 0 { (1, val.wrapping_neg()) } else { (0, val) };
         self.put_unsigned( (neg as u64) | ((val as u64) << 1) );
     }
 
+    /// Same as [BipackSink::put_signed] but fails with [BipackError::Overflow] on `i64::MIN`,
+    /// which it can't represent, writing nothing.
+    fn put_signed_checked(self: &mut Self, val: i64) -> Result<()> {
+        if val == i64::MIN {
+            return Err(BipackError::Overflow);
+        }
+        self.put_signed(val);
+        Ok(())
+    }
+
+    /// Put any `i64` in the shortest of two forms, so it never takes more than the fixed
+    /// `i64` plus one byte. The smartint header holds the form in its lowest bit: 0 means
+    /// the rest is the zig-zag encoded value, as in [BipackSink::put_nanos_delta], 1 means
+    /// the rest is width (1, 2, 4 or 8) of the following big-endian two's complement value.
+    /// Small values of either sign take 1 byte, `i64::MIN` takes 9.
+    fn put_signed_compact(self: &mut Self, value: i64) {
+        let zigzag = zigzag_encode(value);
+        let width = [1, 2, 4, 8].into_iter()
+            .find(|w| value == (value << (64 - w * 8)) >> (64 - w * 8))
+            .unwrap_or(8);
+        let compact_size = match zigzag.checked_shl(1) {
+            Some(header) if zigzag >> 63 == 0 => {
+                let mut size = SizeSink::new();
+                size.put_unsigned(header);
+                size.len()
+            }
+            _ => usize::MAX,
+        };
+        if compact_size <= width + 1 {
+            self.put_unsigned(zigzag << 1);
+        } else {
+            self.put_unsigned(((width as u64) << 1) | 1);
+            self.put_slice(&value.to_be_bytes()[8 - width..]);
+        }
+    }
+
+    /// Put a histogram (frequency table) in a compact form: number of buckets, then for
+    /// each bucket in ascending key order the key delta from the previous key and the count,
+    /// all as smartints. Is very effective for sparse histograms, and the result does not
+    /// depend on insertion order. Use
+    /// [crate::bipack_source::BipackSource::get_histogram] to unpack it.
+    fn put_histogram(self: &mut Self, counts: &BTreeMap<u64, u64>) {
+        self.put_unsigned(counts.len());
+        let mut last = 0u64;
+        for (key, count) in counts {
+            self.put_unsigned(key - last);
+            self.put_unsigned(*count);
+            last = *key;
+        }
+    }
+
+    /// Put up to 64 flags as a single smartint, so the most often used low flags take
+    /// less space. This is the recommended way to pack flag words, e.g. with `bitflags`
+    /// crate pack `flags.bits()` and unpack with `from_bits` or `from_bits_retain` after
+    /// [crate::bipack_source::BipackSource::get_flags].
+    fn put_flags(self: &mut Self, bits: u64) {
+        self.put_unsigned(bits);
+    }
+
+    /// Put boolean array as a bitset: the size as smartint, then bits packed 8 per byte,
+    /// most significant bit first. The last byte is zero-padded.
+    fn put_bits(self: &mut Self, bits: &[bool]) {
+        self.put_unsigned(bits.len());
+        for chunk in bits.chunks(8) {
+            let mut byte = 0u8;
+            for (i, b) in chunk.iter().enumerate() {
+                if *b { byte |= 0x80 >> i; }
+            }
+            self.put_u8(byte);
+        }
+    }
+
+    /// Put a sequence of items as they are produced by the iterator, without collecting
+    /// them first: the size as smartint followed by each packed item. The result is the
+    /// same as packing a `Vec` of the items, so bytes are written as is.
+    fn put_seq<I, T>(self: &mut Self, items: I)
+    where I: IntoIterator<Item=T>, I::IntoIter: ExactSizeIterator, T: BiPackable, Self: Sized {
+        let items = items.into_iter();
+        self.put_unsigned(items.len());
+        for x in items { x.bi_pack_element(self); }
+    }
+
+    /// Put map as the entries count followed by key and value pairs in ascending key order,
+    /// so equal maps always produce the same bytes regardless of insertion order, which is
+    /// what hashing or signing packed data needs. `HashMap` iteration order is random, so
+    /// it can't be packed canonically; collect it into [BTreeMap] first. Use
+    /// [crate::bipack_source::BipackSource::get_map_sorted] to unpack it.
+    fn put_map_sorted<K: Ord + BiPackable, V: BiPackable>(self: &mut Self, map: &BTreeMap<K, V>)
+    where Self: Sized {
+        self.put_unsigned(map.len());
+        for (key, value) in map {
+            key.bi_pack(self);
+            value.bi_pack(self);
+        }
+    }
+
+    /// Put map of smartint keys to binaries, e.g. contract state, without generic overhead.
+    /// Produces the same bytes as [BipackSink::put_map_sorted] does for this map: count, then
+    /// for each entry in ascending key order the key as smartint and the value as
+    /// [BipackSink::put_var_bytes]. Use [crate::bipack_source::BipackSource::get_u64_map] to
+    /// unpack it.
+    fn put_u64_map(self: &mut Self, map: &BTreeMap<u64, Vec<u8>>) {
+        self.put_unsigned(map.len());
+        for (key, value) in map {
+            self.put_unsigned(*key);
+            self.put_var_bytes(value);
+        }
+    }
+
+    /// Put the value packed as an opaque blob with [BipackSink::put_var_bytes], so readers
+    /// could skip it without knowing its structure. Use
+    /// [crate::bipack_source::BipackSource::get_nested] to unpack it.
+    fn put_nested<T: BiPackable + ?Sized>(self: &mut Self, value: &T) {
+        let mut blob = Vec::new();
+        value.bi_pack(&mut blob);
+        self.put_var_bytes(&blob);
+    }
+
+    /// Put enum variant index (discriminant). It is just a smartint, but this is the
+    /// canonical way to pack enums: the variant index followed by its fields, if any.
+    /// Use [crate::bipack_source::BipackSource::get_variant] to read it.
+    fn put_variant(self: &mut Self, index: u64) {
+        self.put_unsigned(index);
+    }
+
+    /// Put enum variant with its body length-prefixed: `tag` as smartint, then the body
+    /// packed by `f` as [BipackSink::put_var_bytes]. As the reader always knows the body
+    /// size, it can skip or keep variants it does not know, see
+    /// [crate::bipack::RawVariant]. Use
+    /// [crate::bipack_source::BipackSource::get_variant_body] to read it.
+    fn put_variant_body<F: FnOnce(&mut Vec<u8>)>(self: &mut Self, tag: u64, f: F) {
+        let mut body = Vec::new();
+        f(&mut body);
+        self.put_variant(tag);
+        self.put_var_bytes(&body);
+    }
+
+    /// Put integrity-checked block: the body packed by `f`, written as
+    /// [BipackSink::put_var_bytes] followed by 4 bytes of its [crate::tools::crc32].
+    /// Use [crate::bipack_source::BipackSource::get_crc_block] to read and check it.
+    fn put_crc_block<F: FnOnce(&mut Vec<u8>)>(self: &mut Self, f: F) {
+        let mut body = Vec::new();
+        f(&mut body);
+        self.put_var_bytes(&body);
+        self.put_u32(crc32(&body));
+    }
+
     fn put_var_unsigned(self: &mut Self, value: u64) {
         let mut rest = value;
         loop {
             let x = rest & 127;
-            rest = rest >> 7;
+            rest >>= 7;
             if rest > 0 {
                 self.put_u8((x | 0x80) as u8);
             } else {
@@ -155,6 +561,13 @@ pub trait BipackSink {
             if rest == 0 { break; }
         }
     }
+
+    /// Put signed value as zig-zag encoded LEB128 varint, compatible with protobuf `sint64`
+    /// and other LEB128 decoders. Unlike [BipackSink::put_signed] it is not a smartint;
+    /// read it with [crate::bipack_source::BipackSource::get_varint_signed].
+    fn put_varint_signed(self: &mut Self, value: i64) {
+        self.put_var_unsigned(zigzag_encode(value));
+    }
 }
 
 
@@ -162,5 +575,159 @@ impl BipackSink for Vec<u8> {
     fn put_u8(self: &mut Self, data: u8) {
         self.push(data);
     }
+
+    fn put_slice(self: &mut Self, data: &[u8]) {
+        self.extend_from_slice(data);
+    }
+}
+
+/// Sink that writes to [std::io::Write]. As sinks do not return errors, the first
+/// write error is kept and all further data are ignored; check it with
+/// [IoSink::error] or [IoSink::into_inner] when done.
+#[cfg(feature = "std")]
+pub struct IoSink<W: std::io::Write> {
+    writer: W,
+    error: Option<std::io::Error>,
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> IoSink<W> {
+    pub fn new(writer: W) -> IoSink<W> { IoSink { writer, error: None } }
+
+    /// First write error, if any.
+    pub fn error(&self) -> Option<&std::io::Error> { self.error.as_ref() }
+
+    /// Return the writer, or the first write error if it has happened.
+    pub fn into_inner(self) -> std::io::Result<W> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.writer),
+        }
+    }
 }
 
+#[cfg(feature = "std")]
+impl<W: std::io::Write> BipackSink for IoSink<W> {
+    fn put_u8(self: &mut Self, data: u8) {
+        self.put_slice(&[data]);
+    }
+
+    fn put_slice(self: &mut Self, data: &[u8]) {
+        if self.error.is_none() {
+            if let Err(e) = self.writer.write_all(data) { self.error = Some(e); }
+        }
+    }
+}
+
+/// Thin wrapper around [`Vec<u8>`] sink that makes buffer capacity control explicit.
+/// Use [VecSink::with_capacity] when the approximate packed size is known in advance
+/// to avoid reallocations.
+#[derive(Debug, Clone, Default)]
+pub struct VecSink(Vec<u8>);
+
+impl VecSink {
+    pub fn new() -> VecSink { VecSink(Vec::new()) }
+
+    pub fn with_capacity(capacity: usize) -> VecSink { VecSink(Vec::with_capacity(capacity)) }
+
+    /// Return the packed data.
+    pub fn into_inner(self) -> Vec<u8> { self.0 }
+
+    /// Append a record that starts at [RECORD_ALIGNMENT] boundary (relative to the sink
+    /// start), for mmap-friendly random access. The record is packed by `f`, written as
+    /// [BipackSink::put_var_bytes] and then zero-padded to the next boundary. Use
+    /// [crate::bipack_source::MessageIter::aligned] to read such records.
+    pub fn put_aligned_record(&mut self, f: impl FnOnce(&mut Vec<u8>)) {
+        let mut record = Vec::new();
+        f(&mut record);
+        self.pad_to_alignment();
+        self.put_var_bytes(&record);
+        self.pad_to_alignment();
+    }
+
+    fn pad_to_alignment(&mut self) {
+        let size = self.0.len().next_multiple_of(RECORD_ALIGNMENT);
+        self.0.resize(size, 0);
+    }
+}
+
+impl Deref for VecSink {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> { &self.0 }
+}
+
+impl BipackSink for VecSink {
+    fn put_u8(self: &mut Self, data: u8) {
+        self.0.push(data);
+    }
+
+    fn put_slice(self: &mut Self, data: &[u8]) {
+        self.0.extend_from_slice(data);
+    }
+}
+
+/// Sink that writes nothing but counts bytes, to know the exact packed size before
+/// allocating the buffer: pack the value into it first, then into the real sink.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SizeSink(usize);
+
+impl SizeSink {
+    pub fn new() -> SizeSink { SizeSink(0) }
+
+    /// Number of bytes written so far.
+    pub fn len(&self) -> usize { self.0 }
+
+    pub fn is_empty(&self) -> bool { self.0 == 0 }
+}
+
+impl BipackSink for SizeSink {
+    fn put_u8(self: &mut Self, data: u8) {
+        self.0 += 1;
+    }
+
+    fn put_slice(self: &mut Self, data: &[u8]) {
+        self.0 += data.len();
+    }
+}
+
+/// Sink wrapper that packs repeated strings once: [DictSink::put_interned_str] writes the
+/// string index in the dictionary built while packing, as smartint. The index equal to the
+/// dictionary size means a new string, which follows as [BipackSink::put_str] and is added
+/// to the dictionary, so the second occurrence costs only the index, 1 byte for the first
+/// 64 strings. Other values are written to the wrapped sink as is. Use
+/// [crate::bipack_source::DictSource] to unpack.
+pub struct DictSink<S: BipackSink> {
+    sink: S,
+    dictionary: BTreeMap<String, u64>,
+}
+
+impl<S: BipackSink> DictSink<S> {
+    pub fn new(sink: S) -> DictSink<S> { DictSink { sink, dictionary: BTreeMap::new() } }
+
+    pub fn get_ref(&self) -> &S { &self.sink }
+
+    pub fn into_inner(self) -> S { self.sink }
+
+    pub fn put_interned_str(&mut self, str: &str) {
+        match self.dictionary.get(str) {
+            Some(index) => self.sink.put_unsigned(*index),
+            None => {
+                let index = self.dictionary.len() as u64;
+                self.sink.put_unsigned(index);
+                self.sink.put_str(str);
+                self.dictionary.insert(str.to_string(), index);
+            }
+        }
+    }
+}
+
+impl<S: BipackSink> BipackSink for DictSink<S> {
+    fn put_u8(self: &mut Self, data: u8) {
+        self.sink.put_u8(data)
+    }
+
+    fn put_slice(self: &mut Self, data: &[u8]) {
+        self.sink.put_slice(data)
+    }
+}
@@ -12,28 +12,84 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::error::Error;
-use std::fmt::{Display, Formatter};
-use std::string::FromUtf8Error;
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{FromUtf8Error, String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use core::time::Duration;
+use crate::bipack::BiUnpackable;
+use crate::bipack_sink::{RECORD_ALIGNMENT, V0LIMIT, V1LIMIT, V2LIMIT};
+use crate::tools::{crc32, to_dump, zigzag_decode};
 use crate::bipack_source::BipackError::NoDataError;
 
 /// Result of error-aware bipack function
-pub type Result<T> = std::result::Result<T, BipackError>;
+pub type Result<T> = core::result::Result<T, BipackError>;
 
-/// There is not enought data to fulfill the request
+/// Errors that can occur while decoding bipack data. More variants could be added later,
+/// so match it with a wildcard arm.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum BipackError {
-    NoDataError,
+    /// There is not enough data to fulfill the request: `needed` more bytes were expected
+    /// at byte `offset` of the source.
+    NoDataError { offset: usize, needed: usize },
+    /// The data end in the middle of a value that needs at least `needed` more bytes, so
+    /// decoding could be retried when more data arrive. Unlike [BipackError::NoDataError]
+    /// it never means malformed data, see [SliceSource::get_unsigned_incremental].
+    Incomplete { needed: usize },
+    /// Decoded bytes are not a valid UTF-8 string.
     BadEncoding(FromUtf8Error),
+    /// Checksum of the data does not match the stored one.
+    ChecksumMismatch,
+    /// The value is not encoded in its canonical (shortest) form.
+    NonCanonical,
+    /// Packed size of some data is more than allowed `limit`.
+    LimitExceeded { size: u64, limit: usize },
+    /// Decoded value is not valid for its type.
+    InvalidValue,
+    /// Decoded value does not fit the requested type.
+    Overflow,
+    /// Enum tag does not match any known variant.
+    UnknownVariant(u64),
+    /// Decoding is done but `remaining` bytes are left unread.
+    TrailingData { remaining: usize },
+    /// Decoding of a field failed, `path` holds labels of enclosing fields, outermost
+    /// first, see [TracingSource].
+    AtPath { path: Vec<String>, error: Box<BipackError> },
+    /// Reading from the underlying reader failed.
+    #[cfg(feature = "std")]
+    IoError(std::io::ErrorKind),
 }
 
 impl Display for BipackError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            NoDataError { offset, needed } =>
+                write!(f, "not enough data at offset {}: {} more byte(s) needed", offset, needed),
+            BipackError::Incomplete { needed } =>
+                write!(f, "incomplete value: at least {} more byte(s) needed", needed),
+            BipackError::BadEncoding(e) => write!(f, "bad UTF-8 encoding: {}", e),
+            BipackError::ChecksumMismatch => write!(f, "checksum mismatch"),
+            BipackError::NonCanonical => write!(f, "non-canonical encoding"),
+            BipackError::LimitExceeded { size, limit } =>
+                write!(f, "size {} exceeds limit {}", size, limit),
+            BipackError::InvalidValue => write!(f, "invalid value"),
+            BipackError::Overflow => write!(f, "value does not fit the type"),
+            BipackError::UnknownVariant(tag) => write!(f, "unknown enum variant {}", tag),
+            BipackError::TrailingData { remaining } =>
+                write!(f, "{} unread byte(s) after the data", remaining),
+            BipackError::AtPath { path, error } => write!(f, "{}: {}", path.join("."), error),
+            #[cfg(feature = "std")]
+            BipackError::IoError(kind) => write!(f, "I/O error: {}", kind),
+        }
     }
 }
 
-impl Error for BipackError {}
+#[cfg(feature = "std")]
+impl std::error::Error for BipackError {}
 
 
 /// Data source compatible with mp_bintools serialization. It supports
@@ -50,17 +106,46 @@ impl Error for BipackError {}
 pub trait BipackSource {
     fn get_u8(self: &mut Self) -> Result<u8>;
 
+    /// Read fixed size big-endian u16 packed with [crate::bipack_sink::BipackSink::put_u16].
+    /// Use [BipackSource::get_u16_le] for little-endian data.
     fn get_u16(self: &mut Self) -> Result<u16> {
         Ok(((self.get_u8()? as u16) << 8) + (self.get_u8()? as u16))
     }
+    /// Read 24-bit big-endian unsigned packed with [crate::bipack_sink::BipackSink::put_u24].
+    fn get_u24(self: &mut Self) -> Result<u32> {
+        let mut bytes = [0u8; 4];
+        self.get_fixed_bytes_into(&mut bytes[1..])?;
+        Ok(u32::from_be_bytes(bytes))
+    }
+    /// Read fixed size big-endian u32, see [BipackSource::get_u16].
     fn get_u32(self: &mut Self) -> Result<u32> {
         Ok(((self.get_u16()? as u32) << 16) + (self.get_u16()? as u32))
     }
 
+    /// Read fixed size big-endian u64, see [BipackSource::get_u16].
     fn get_u64(self: &mut Self) -> Result<u64> {
         Ok(((self.get_u32()? as u64) << 32) | (self.get_u32()? as u64))
     }
 
+    /// Read little-endian u16 packed with [crate::bipack_sink::BipackSink::put_u16_le].
+    fn get_u16_le(self: &mut Self) -> Result<u16> {
+        let mut bytes = [0u8; 2];
+        self.get_fixed_bytes_into(&mut bytes)?;
+        Ok(u16::from_le_bytes(bytes))
+    }
+    /// Read little-endian u32 packed with [crate::bipack_sink::BipackSink::put_u32_le].
+    fn get_u32_le(self: &mut Self) -> Result<u32> {
+        let mut bytes = [0u8; 4];
+        self.get_fixed_bytes_into(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+    /// Read little-endian u64 packed with [crate::bipack_sink::BipackSink::put_u64_le].
+    fn get_u64_le(self: &mut Self) -> Result<u64> {
+        let mut bytes = [0u8; 8];
+        self.get_fixed_bytes_into(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
     fn get_i64(self: &mut Self) -> Result<i64> {
         Ok(self.get_u64()? as i64)
     }
@@ -74,27 +159,173 @@ pub trait BipackSource {
         Ok(self.get_u8()? as i8)
     }
 
+    /// Read float packed with [crate::bipack_sink::BipackSink::put_f32].
+    fn get_f32(self: &mut Self) -> Result<f32> {
+        Ok(f32::from_bits(self.get_u32()?))
+    }
+    /// Read float packed with [crate::bipack_sink::BipackSink::put_f64].
+    fn get_f64(self: &mut Self) -> Result<f64> {
+        Ok(f64::from_bits(self.get_u64()?))
+    }
+
+    /// Read duration packed with [crate::bipack_sink::BipackSink::put_duration]. Returns
+    /// [BipackError::InvalidValue] if nanoseconds are out of range.
+    fn get_duration(self: &mut Self) -> Result<Duration> {
+        let seconds = self.get_unsigned()?;
+        let nanos = self.get_unsigned()?;
+        if nanos >= 1_000_000_000 { return Err(BipackError::InvalidValue); }
+        Ok(Duration::new(seconds, nanos as u32))
+    }
+
+    /// Read nanoseconds delta packed with [crate::bipack_sink::BipackSink::put_nanos_delta].
+    fn get_nanos_delta(self: &mut Self) -> Result<i64> {
+        let value = self.get_unsigned()?;
+        Ok(zigzag_decode(value))
+    }
+
+    /// Read decimal number packed with [crate::bipack_sink::BipackSink::put_fixed_point]
+    /// as `(mantissa, scale)`.
+    fn get_fixed_point(self: &mut Self) -> Result<(i64, u8)> {
+        let value = self.get_unsigned()?;
+        let mantissa = zigzag_decode(value);
+        Ok((mantissa, self.get_u8()?))
+    }
+
+    /// Read time packed with [crate::bipack_sink::BipackSink::put_system_time].
+    #[cfg(feature = "std")]
+    fn get_system_time(self: &mut Self) -> Result<std::time::SystemTime> {
+        std::time::UNIX_EPOCH.checked_add(self.get_duration()?).ok_or(BipackError::InvalidValue)
+    }
+
+    /// Read IP address packed with [crate::bipack_sink::BipackSink::put_ip_addr]. Unknown
+    /// address family tag is [BipackError::InvalidValue].
+    #[cfg(feature = "std")]
+    fn get_ip_addr(self: &mut Self) -> Result<std::net::IpAddr> {
+        match self.get_u8()? {
+            4 => Ok(std::net::Ipv4Addr::from(self.get_exact::<4>()?).into()),
+            6 => Ok(std::net::Ipv6Addr::from(self.get_exact::<16>()?).into()),
+            _ => Err(BipackError::InvalidValue),
+        }
+    }
+
+    /// Read socket address packed with [crate::bipack_sink::BipackSink::put_socket_addr].
+    #[cfg(feature = "std")]
+    fn get_socket_addr(self: &mut Self) -> Result<std::net::SocketAddr> {
+        let ip = self.get_ip_addr()?;
+        Ok(std::net::SocketAddr::new(ip, self.get_unsigned_as()?))
+    }
+
+    /// Read matrix packed with [crate::bipack_sink::BipackSink::put_f32_matrix], returns
+    /// `(rows, cols, data)` where data are row-major. Fails with [BipackError::Overflow] if
+    /// `rows * cols` does not fit `usize`.
+    fn get_f32_matrix(self: &mut Self) -> Result<(usize, usize, Vec<f32>)> {
+        let rows = self.get_unsigned()? as usize;
+        let cols = self.get_unsigned()? as usize;
+        let size = rows.checked_mul(cols).ok_or(BipackError::Overflow)?;
+        let mut result = Vec::new();
+        for i in 0..size { result.push(self.get_f32()?); }
+        Ok((rows, cols, result))
+    }
+
     /// Unpack variable-length packed unsigned value, used aslo internally to store size
     /// of arrays, binary data, strings, etc. To pack use
     /// [crate::bipack_sink::BipackSink::put_unsigned()].
+    ///
+    /// It is lenient: non-canonical (longer than needed) encodings are accepted and bits
+    /// that do not fit `u64` are dropped. Use [BipackSource::get_unsigned_canonical] or
+    /// [SliceSource::strict] to reject such data.
     fn get_unsigned(self: &mut Self) -> Result<u64> {
-        let mut get = || -> Result<u64> { Ok(self.get_u8()? as u64) };
-        let first = get()?;
-        let mut ty = first & 3;
+        read_unsigned(self)
+    }
 
+    /// Read `u128` packed with [crate::bipack_sink::BipackSink::put_unsigned_u128], also
+    /// any smartint. Unlike [BipackSource::get_unsigned] it does not drop extra bits: the value
+    /// that does not fit `u128` causes [BipackError::Overflow].
+    fn get_unsigned_u128(self: &mut Self) -> Result<u128> {
+        let first = self.get_u8()?;
+        let mut result = (first >> 2) as u128;
+        match first & 3 {
+            0 => return Ok(result),
+            1 => return Ok(result | (self.get_u8()? as u128) << 6),
+            _ => {}
+        }
+        result |= (self.get_u8()? as u128) << 6;
+        result |= (self.get_u8()? as u128) << 14;
+        if first & 3 == 2 { return Ok(result); }
+        let mut shift = 22;
+        loop {
+            let x = self.get_u8()?;
+            let bits = (x & 0x7F) as u128;
+            if shift >= 128 || (bits << shift) >> shift != bits { return Err(BipackError::Overflow); }
+            result |= bits << shift;
+            if x & 0x80 == 0 { return Ok(result); }
+            shift += 7;
+        }
+    }
 
-        let mut result = first >> 2;
-        if ty == 0 { return Ok(result); }
-        ty -= 1;
+    /// Read integers packed with [crate::bipack_sink::BipackSink::put_unsigned_slice].
+    fn get_unsigned_vec(self: &mut Self) -> Result<Vec<u64>> {
+        let count = self.get_unsigned()?;
+        let mut result = Vec::new();
+        for i in 0..count { result.push(self.get_unsigned()?); }
+        Ok(result)
+    }
 
-        result = result + (get()? << 6);
-        if ty == 0 { return Ok(result); }
-        ty -= 1;
+    /// Read integers packed with [crate::bipack_sink::BipackSink::put_sorted_unsigned].
+    /// Returns [BipackError::Overflow] if the sum of deltas does not fit u64.
+    fn get_sorted_unsigned(self: &mut Self) -> Result<Vec<u64>> {
+        let count = self.get_unsigned()?;
+        let mut result = Vec::new();
+        let mut last = 0u64;
+        for i in 0..count {
+            last = last.checked_add(self.get_unsigned()?).ok_or(BipackError::Overflow)?;
+            result.push(last);
+        }
+        Ok(result)
+    }
 
-        result = result + (get()? << 14);
-        if ty == 0 { return Ok(result); }
+    /// Unpack smartint like [BipackSource::get_unsigned] and also return the number of bytes
+    /// it occupied, e.g. to mark field boundaries in a dump.
+    fn get_unsigned_counted(self: &mut Self) -> Result<(u64, usize)> {
+        let mut counter = CountingSource { source: self, count: 0 };
+        let value = counter.get_unsigned()?;
+        Ok((value, counter.count))
+    }
 
-        Ok(result | (self.get_varint_unsigned()? << 22))
+    /// Unpack smartint like [BipackSource::get_unsigned] but only if it is packed in the
+    /// shortest possible form, as [crate::bipack_sink::BipackSink::put_unsigned] does,
+    /// otherwise return [BipackError::NonCanonical]. E.g. value below 64 packed in 2 bytes
+    /// is rejected. Use it when packed bytes are hashed or signed and each value must have
+    /// exactly one binary representation.
+    fn get_unsigned_canonical(self: &mut Self) -> Result<u64> {
+        let first = self.get_u8()? as u64;
+        let ty = first & 3;
+        let mut result = first >> 2;
+        if ty >= 1 { result += (self.get_u8()? as u64) << 6; }
+        if ty >= 2 { result += (self.get_u8()? as u64) << 14; }
+        let minimal = match ty {
+            0 => true,
+            1 => result >= V0LIMIT,
+            2 => result >= V1LIMIT,
+            _ => {
+                // the varint tail holds at most 42 bits and its last byte can't be zero
+                let mut tail = 0u64;
+                let mut count = 0;
+                loop {
+                    let x = self.get_u8()? as u64;
+                    if count > 35 { return Err(BipackError::NonCanonical); }
+                    tail |= (x & 0x7F) << count;
+                    if (x & 0x80) == 0 {
+                        if x == 0 || tail >= 1 << 42 { return Err(BipackError::NonCanonical); }
+                        break;
+                    }
+                    count += 7;
+                }
+                result |= tail << 22;
+                result >= V2LIMIT
+            }
+        };
+        if minimal { Ok(result) } else { Err(BipackError::NonCanonical) }
     }
 
     /// Unpack variable-length signed value, packed with
@@ -105,37 +336,98 @@ pub trait BipackSource {
         Ok(if value & 1 != 0 { -result } else { result } )
     }
 
+    /// Read signed value packed with [crate::bipack_sink::BipackSink::put_signed_compact].
+    /// Width of the fixed form other than 1, 2, 4 or 8 causes [BipackError::InvalidValue].
+    fn get_signed_compact(self: &mut Self) -> Result<i64> {
+        let header = self.get_unsigned()?;
+        if header & 1 == 0 {
+            return Ok(zigzag_decode(header >> 1));
+        }
+        let width = match header >> 1 {
+            w @ (1 | 2 | 4 | 8) => w as usize,
+            _ => return Err(BipackError::InvalidValue),
+        };
+        let mut bytes = [0u8; 8];
+        self.get_fixed_bytes_into(&mut bytes[..width])?;
+        Ok(i64::from_be_bytes(bytes) >> (64 - width * 8))
+    }
+
     /// read 8-bytes varint-packed unsigned value from the source. We dont' recommend
     /// using it directly; use [BipackSource::get_unsigned] instead.
     fn get_varint_unsigned(self: &mut Self) -> Result<u64> {
-        let mut result = 0u64;
-        let mut count = 0;
-        loop {
-            let x = self.get_u8()? as u64;
-            result = result | ((x & 0x7F) << count);
-            if (x & 0x80) == 0 { return Ok(result); }
-            count += 7
-        }
+        read_varint(self)
     }
 
+    /// Read zig-zag encoded LEB128 signed varint written by
+    /// [crate::bipack_sink::BipackSink::put_varint_signed], e.g. protobuf `sint64`.
+    fn get_varint_signed(self: &mut Self) -> Result<i64> {
+        let value = self.get_varint_unsigned()?;
+        Ok(zigzag_decode(value))
+    }
+
+    /// Read smartint-encoded unsigned value like [BipackSource::get_unsigned] and convert
+    /// it to `T`, returning [BipackError::Overflow] if it does not fit instead of truncating.
+    fn get_unsigned_as<T: TryFrom<u64>>(self: &mut Self) -> Result<T> {
+        T::try_from(self.get_unsigned()?).map_err(|_| BipackError::Overflow)
+    }
+
+    /// read 1-byte unsigned value from the source as smartint-encoded, same as
+    /// [BipackSource::get_unsigned_as] u8, so bigger values cause [BipackError::Overflow].
+    fn get_packed_u8(self: &mut Self) -> Result<u8> { self.get_unsigned_as() }
+
     /// read 2-bytes unsigned value from the source as smartint-encoded, same as
-    /// [BipackSource::get_unsigned] as u16
+    /// [BipackSource::get_unsigned_as] u16, so bigger values cause [BipackError::Overflow].
     fn get_packed_u16(self: &mut Self) -> Result<u16> {
-        Ok(self.get_unsigned()? as u16)
+        self.get_unsigned_as()
     }
 
-    /// read 4-bytes unsigned value from the source
-    /// read 2-bytes unsigned value from the source as smartint-encoded, same as
-    /// [BipackSource::get_unsigned] as u32.
-    fn get_packed_u32(self: &mut Self) -> Result<u32> { Ok(self.get_unsigned()? as u32) }
+    /// read 4-bytes unsigned value from the source as smartint-encoded, same as
+    /// [BipackSource::get_unsigned_as] u32, so bigger values cause [BipackError::Overflow].
+    fn get_packed_u32(self: &mut Self) -> Result<u32> { self.get_unsigned_as() }
 
-    /// read exact number of bytes from the source as a vec.
+    /// read exact number of bytes from the source as a vec. The size could come from
+    /// untrusted data, so the buffer grows as bytes are read rather than being allocated
+    /// upfront.
     fn get_fixed_bytes(self: &mut Self, size: usize) -> Result<Vec<u8>> {
-        let mut result = Vec::with_capacity(size);
+        let mut result = Vec::with_capacity(size.min(4096));
         for i in 0..size { result.push(self.get_u8()?); }
         Ok(result)
     }
 
+    /// Read signed bytes packed with [crate::bipack_sink::BipackSink::put_fixed_i8].
+    fn get_fixed_i8(self: &mut Self, size: usize) -> Result<Vec<i8>> {
+        Ok(self.get_fixed_bytes(size)?.into_iter().map(|b| b as i8).collect())
+    }
+
+    /// Read exactly `buf.len()` bytes from the source into the caller-provided buffer,
+    /// without allocation. Fails with [BipackError::NoDataError] if the source runs dry,
+    /// in which case the buffer content is unspecified.
+    fn get_fixed_bytes_into(self: &mut Self, buf: &mut [u8]) -> Result<()> {
+        for b in buf.iter_mut() { *b = self.get_u8()?; }
+        Ok(())
+    }
+
+    /// Read fixed-width field packed with [crate::bipack_sink::BipackSink::put_exact].
+    fn get_exact<const N: usize>(self: &mut Self) -> Result<[u8; N]> {
+        let mut result = [0u8; N];
+        self.get_fixed_bytes_into(&mut result)?;
+        Ok(result)
+    }
+
+    /// Read all the remaining bytes, e.g. the trailing payload packed without size as the
+    /// last field. Reads until the source reports [BipackError::NoDataError], so it is
+    /// empty if there is nothing left; other errors are returned as is.
+    fn get_rest(self: &mut Self) -> Result<Vec<u8>> {
+        let mut result = Vec::new();
+        loop {
+            match self.get_u8() {
+                Ok(b) => result.push(b),
+                Err(NoDataError { .. }) => return Ok(result),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
     /// Read variable-length byte array from the source (with packed size), created
     /// by [crate::bipack_sink::BipackSink::put_var_bytes] or
     /// [crate::bipack_sink::BipackSink::put_str]. The size is encoded the same way as does
@@ -146,13 +438,217 @@ pub trait BipackSource {
         self.get_fixed_bytes(size)
     }
 
+    /// Read binary packed with [crate::bipack_sink::BipackSink::put_var_bytes_compressed],
+    /// inflating it if needed. Unknown flag or corrupted deflated data cause
+    /// [BipackError::InvalidValue]. Note that the inflated size is not limited.
+    #[cfg(feature = "flate2")]
+    fn get_var_bytes_compressed(self: &mut Self) -> Result<Vec<u8>> {
+        use std::io::Read;
+        match self.get_u8()? {
+            0 => self.get_var_bytes(),
+            1 => {
+                let deflated = self.get_var_bytes()?;
+                let mut result = Vec::new();
+                flate2::read::DeflateDecoder::new(deflated.as_slice())
+                    .read_to_end(&mut result)
+                    .map_err(|_| BipackError::InvalidValue)?;
+                Ok(result)
+            }
+            _ => Err(BipackError::InvalidValue),
+        }
+    }
+
+    /// Read variable-length byte array like [BipackSource::get_var_bytes] into existing
+    /// `out` buffer, reusing its allocation, and return its size. On any error `out` is
+    /// left empty.
+    fn get_var_bytes_into(self: &mut Self, out: &mut Vec<u8>) -> Result<usize> {
+        out.clear();
+        let size = self.get_unsigned()? as usize;
+        read_bytes_into(self, size, out)?;
+        Ok(size)
+    }
+
     /// REad a variable length string from a source packed with
     /// [crate::bipack_sink::BipackSink::put_str]. It is a variable sized array fo utf8 encoded
     /// characters.
     fn get_str(self: &mut Self) -> Result<String> {
         String::from_utf8(
             self.get_var_bytes()?
-        ).or_else(|e| Err(BipackError::BadEncoding(e)))
+        ).map_err(BipackError::BadEncoding)
+    }
+
+    /// Read a string like [BipackSource::get_str] but replace invalid UTF-8 sequences with
+    /// `U+FFFD` instead of failing, e.g. to display partially corrupted data. Valid strings
+    /// are not copied.
+    fn get_str_lossy(self: &mut Self) -> Result<String> {
+        Ok(String::from_utf8(self.get_var_bytes()?)
+            .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned()))
+    }
+
+    /// Read a string like [BipackSource::get_str] but without UTF-8 validation, to save
+    /// time on big strings in trusted pipelines where it is already validated.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee the packed string is a valid UTF-8, otherwise the
+    /// returned `String` breaks its invariant and using it is undefined behavior.
+    unsafe fn get_str_unchecked(self: &mut Self) -> Result<String> {
+        Ok(String::from_utf8_unchecked(self.get_var_bytes()?))
+    }
+
+    /// Read a string like [BipackSource::get_str] into existing `out` buffer, reusing its
+    /// allocation, e.g. in a decoding loop. On any error `out` is left empty.
+    fn get_str_into(self: &mut Self, out: &mut String) -> Result<()> {
+        out.clear();
+        let size = self.get_unsigned()? as usize;
+        read_str_into(self, size, out)
+    }
+
+    /// Read UTF-16 string packed with [crate::bipack_sink::BipackSink::put_str_utf16].
+    /// Unpaired surrogates cause [BipackError::InvalidValue].
+    fn get_str_utf16(self: &mut Self) -> Result<String> {
+        let count = self.get_unsigned()?;
+        let mut units = Vec::new();
+        for i in 0..count { units.push(self.get_u16_le()?); }
+        String::from_utf16(&units).map_err(|_| BipackError::InvalidValue)
+    }
+
+    /// Read optional string packed with [crate::bipack_sink::BipackSink::put_opt_str].
+    /// Its size is checked against [SliceSource] limits the same way as in
+    /// [BipackSource::get_str].
+    fn get_opt_str(self: &mut Self) -> Result<Option<String>> {
+        match self.get_unsigned()? {
+            0 => Ok(None),
+            size => String::from_utf8(self.get_fixed_bytes(size as usize - 1)?)
+                .map(Some)
+                .map_err(BipackError::BadEncoding),
+        }
+    }
+
+    /// Read a string like [BipackSource::get_str] but fail with [BipackError::LimitExceeded]
+    /// if its packed size is greater than `max_len` bytes. The size is checked before reading
+    /// and allocating anything, so it is safe for untrusted input.
+    fn get_str_max(self: &mut Self, max_len: usize) -> Result<String> {
+        let size = self.get_unsigned()?;
+        if size > max_len as u64 {
+            return Err(BipackError::LimitExceeded { size, limit: max_len });
+        }
+        String::from_utf8(
+            self.get_fixed_bytes(size as usize)?
+        ).map_err(BipackError::BadEncoding)
+    }
+
+    /// Read flags packed with [crate::bipack_sink::BipackSink::put_flags].
+    fn get_flags(self: &mut Self) -> Result<u64> {
+        self.get_unsigned()
+    }
+
+    /// Read boolean array packed with [crate::bipack_sink::BipackSink::put_bits].
+    fn get_bits(self: &mut Self) -> Result<Vec<bool>> {
+        let count = self.get_unsigned()?;
+        let mut result = Vec::new();
+        let mut byte = 0u8;
+        for i in 0..count {
+            if i % 8 == 0 { byte = self.get_u8()?; }
+            result.push(byte & (0x80 >> (i % 8)) != 0);
+        }
+        Ok(result)
+    }
+
+    /// Read a sequence packed with [crate::bipack_sink::BipackSink::put_seq] element by
+    /// element, passing each to `f` without collecting them. Stops on the first error
+    /// returned by either decoding or `f`.
+    fn for_each_element<T, F>(self: &mut Self, mut f: F) -> Result<()>
+    where T: BiUnpackable, F: FnMut(T) -> Result<()>, Self: Sized {
+        let count = self.get_unsigned()?;
+        for i in 0..count { f(T::bi_unpack_element(self)?)?; }
+        Ok(())
+    }
+
+    /// Read a sequence packed with [crate::bipack_sink::BipackSink::put_seq] into a
+    /// [smallvec::SmallVec] that keeps up to `N` elements inline, so short sequences need
+    /// no heap. The count is not limited by [SliceSource::with_max_var_len], which is for
+    /// binaries and strings, but space is never reserved beyond `N` elements in advance:
+    /// a malicious big count fails with [BipackError::NoDataError] when data run out.
+    #[cfg(feature = "smallvec")]
+    fn get_smallvec<T: BiUnpackable, const N: usize>(self: &mut Self) -> Result<smallvec::SmallVec<[T; N]>>
+    where Self: Sized {
+        let count = self.get_unsigned()?;
+        let mut result = smallvec::SmallVec::new();
+        for i in 0..count { result.push(T::bi_unpack_element(self)?); }
+        Ok(result)
+    }
+
+    /// Read a value packed with [crate::bipack_sink::BipackSink::put_nested]. It is
+    /// decoded from its blob only, so it can't read past it. Unread rest of the blob, if
+    /// any, is ignored, so newer versions of the value could add fields to the end.
+    fn get_nested<T: BiUnpackable>(self: &mut Self) -> Result<T> {
+        let blob = self.get_var_bytes()?;
+        T::bi_unpack(&mut SliceSource::from(&blob))
+    }
+
+    /// Read enum variant index packed with [crate::bipack_sink::BipackSink::put_variant].
+    fn get_variant(self: &mut Self) -> Result<u64> {
+        self.get_unsigned()
+    }
+
+    /// Read enum variant packed with [crate::bipack_sink::BipackSink::put_variant_body],
+    /// returns its tag and body.
+    fn get_variant_body(self: &mut Self) -> Result<(u64, Vec<u8>)> {
+        let tag = self.get_variant()?;
+        Ok((tag, self.get_var_bytes()?))
+    }
+
+    /// Read block packed with [crate::bipack_sink::BipackSink::put_crc_block], returns its
+    /// body or [BipackError::ChecksumMismatch] if the body is corrupted.
+    fn get_crc_block(self: &mut Self) -> Result<Vec<u8>> {
+        let body = self.get_var_bytes()?;
+        if self.get_u32()? != crc32(&body) {
+            return Err(BipackError::ChecksumMismatch);
+        }
+        Ok(body)
+    }
+
+    /// Read a histogram packed with [crate::bipack_sink::BipackSink::put_histogram]. Fails
+    /// with [BipackError::Overflow] if the key deltas sum up beyond `u64::MAX`.
+    fn get_histogram(self: &mut Self) -> Result<BTreeMap<u64, u64>> {
+        let size = self.get_unsigned()?;
+        let mut result = BTreeMap::new();
+        let mut key = 0u64;
+        for i in 0..size {
+            key = key.checked_add(self.get_unsigned()?).ok_or(BipackError::Overflow)?;
+            result.insert(key, self.get_unsigned()?);
+        }
+        Ok(result)
+    }
+
+    /// Read map packed with [crate::bipack_sink::BipackSink::put_u64_map]. Unlike
+    /// [BipackSource::get_map_sorted] keys must be strictly ascending, otherwise it fails
+    /// with [BipackError::NonCanonical], so only the canonical encoding is accepted.
+    fn get_u64_map(self: &mut Self) -> Result<BTreeMap<u64, Vec<u8>>> {
+        let size = self.get_unsigned()?;
+        let mut result = BTreeMap::new();
+        let mut last: Option<u64> = None;
+        for i in 0..size {
+            let key = self.get_unsigned()?;
+            if last.is_some_and(|l| l >= key) { return Err(BipackError::NonCanonical); }
+            last = Some(key);
+            result.insert(key, self.get_var_bytes()?);
+        }
+        Ok(result)
+    }
+
+    /// Read map packed with [crate::bipack_sink::BipackSink::put_map_sorted]. Duplicate
+    /// keys are not checked, the last value wins.
+    fn get_map_sorted<K: Ord + BiUnpackable, V: BiUnpackable>(self: &mut Self) -> Result<BTreeMap<K, V>>
+    where Self: Sized {
+        let size = self.get_unsigned()?;
+        let mut result = BTreeMap::new();
+        for i in 0..size {
+            let key = K::bi_unpack(self)?;
+            result.insert(key, V::bi_unpack(self)?);
+        }
+        Ok(result)
     }
 }
 
@@ -161,24 +657,581 @@ pub trait BipackSource {
 pub struct SliceSource<'a> {
     data: &'a [u8],
     position: usize,
+    max_var_len: usize,
+    strict: bool,
 }
 
 impl<'a> SliceSource<'a> {
-    pub fn from(src: &'a [u8]) -> SliceSource {
-        SliceSource { data: src, position: 0 }
+    pub fn from(src: &'a [u8]) -> SliceSource<'a> {
+        SliceSource { data: src, position: 0, max_var_len: usize::MAX, strict: false }
+    }
+
+    /// Create source in strict mode to decode untrusted data, see [SliceSource::set_strict].
+    pub fn strict(src: &'a [u8]) -> SliceSource<'a> {
+        SliceSource { strict: true, ..SliceSource::from(src) }
+    }
+
+    /// Switch strict mode. In strict mode all smartints, including sizes, must be canonical
+    /// as with [BipackSource::get_unsigned_canonical], varints must fit `u64` and be
+    /// canonical too, and sizes of binaries and strings are checked against the remaining
+    /// data before any allocation. Limit set with [SliceSource::with_max_var_len] applies
+    /// in both modes. Sources are lenient by default for backward compatibility.
+    pub fn set_strict(&mut self, strict: bool) { self.strict = strict }
+
+    /// Create source that refuses variable-length binaries and strings longer than `limit`
+    /// with [BipackError::LimitExceeded], checking the size before any allocation. Use it
+    /// to decode untrusted data.
+    pub fn with_max_var_len(src: &'a [u8], limit: usize) -> SliceSource<'a> {
+        SliceSource { max_var_len: limit, ..SliceSource::from(src) }
+    }
+
+    /// Current read position, e.g. number of bytes already consumed.
+    pub fn position(&self) -> usize { self.position }
+
+    /// Number of bytes not yet consumed.
+    pub fn remaining(&self) -> usize { self.data.len() - self.position }
+
+    /// Read smartint from partially received data: if the data end in the middle of it,
+    /// return [BipackError::Incomplete] with the number of missing bytes, computed from the
+    /// type bits, and leave the position unchanged, so the incremental parser could wait for
+    /// more data and retry. For type 3 the varint tail length is not known in advance, so
+    /// `needed` is the lower bound: 1 if the received tail is not terminated yet.
+    pub fn get_unsigned_incremental(&mut self) -> Result<u64> {
+        let rest = &self.data[self.position..];
+        let size = match rest.first() {
+            None => return Err(BipackError::Incomplete { needed: 1 }),
+            Some(first) if first & 3 == 3 => match rest.iter().skip(3).position(|b| b & 0x80 == 0) {
+                Some(tail) => 3 + tail + 1,
+                None => rest.len().max(3) + 1,
+            },
+            Some(first) => (first & 3) as usize + 1,
+        };
+        if size > rest.len() {
+            return Err(BipackError::Incomplete { needed: size - rest.len() });
+        }
+        self.get_unsigned()
+    }
+
+    /// Read the smartint size of the next binary or string without consuming it and return
+    /// it with the number of bytes the size takes, e.g. to check it against
+    /// [SliceSource::remaining] first. The position is not changed even on error.
+    pub fn peek_var_len(&mut self) -> Result<(u64, usize)> {
+        let start = self.position;
+        let result = self.get_unsigned();
+        let prefix_len = self.position - start;
+        self.position = start;
+        Ok((result?, prefix_len))
+    }
+
+    /// Human-readable dump of not yet consumed data, see [to_dump]. Useful to report
+    /// decoding errors.
+    pub fn dump_remaining(&self) -> String {
+        to_dump(&self.data[self.position..])
+    }
+
+    /// Human-readable dump of all the data, see [to_dump], preceded with the line showing
+    /// the current position.
+    pub fn dump_all(&self) -> String {
+        format!("position {:04X} of {:04X}\n{}", self.position, self.data.len(), to_dump(self.data))
+    }
+
+    /// Check that all the data were read, otherwise return [BipackError::TrailingData].
+    /// Call it when the message is decoded to catch framing errors.
+    pub fn finish(self) -> Result<()> {
+        match self.data.len() - self.position {
+            0 => Ok(()),
+            remaining => Err(BipackError::TrailingData { remaining }),
+        }
+    }
+
+    /// Rewind to the start of data to read it again.
+    pub fn reset(&mut self) { self.position = 0 }
+
+    /// Set read position. It could be equal to the data size (nothing left to read) but
+    /// not greater, in which case it returns [BipackError::NoDataError].
+    pub fn seek(&mut self, position: usize) -> Result<()> {
+        if position > self.data.len() {
+            return Err(NoDataError { offset: self.data.len(), needed: position - self.data.len() });
+        }
+        self.position = position;
+        Ok(())
+    }
+
+    /// Iterate over remaining bytes, advancing the same position as typed accessors do,
+    /// e.g. `src.bytes().take(4)` consumes 4 bytes. Stops at the end of data.
+    pub fn bytes(&mut self) -> SliceBytes<'_, 'a> {
+        SliceBytes { source: self }
+    }
+
+    /// Read a string like [BipackSource::get_str] but borrow it from the source data
+    /// without copying, so it lives as long as the data, not the source. It is always
+    /// [Cow::Borrowed] here; streaming sources could return [Cow::Owned] with the same API.
+    pub fn get_str_cow(&mut self) -> Result<Cow<'a, str>> {
+        let size = self.get_var_size()?;
+        let rest = self.data.len() - self.position;
+        if size > rest {
+            return Err(NoDataError { offset: self.position, needed: size - rest });
+        }
+        let data: &'a [u8] = self.data;
+        let bytes = &data[self.position..self.position + size];
+        let result = core::str::from_utf8(bytes).map_err(|_| {
+            BipackError::BadEncoding(String::from_utf8(bytes.to_vec()).unwrap_err())
+        })?;
+        self.position += size;
+        Ok(Cow::Borrowed(result))
+    }
+
+    /// Consume all the remaining bytes like [BipackSource::get_rest] but borrow them from
+    /// the source data without copying.
+    pub fn get_rest_borrowed(&mut self) -> &'a [u8] {
+        let data: &'a [u8] = self.data;
+        let result = &data[self.position..];
+        self.position = data.len();
+        result
+    }
+
+    /// Read size of binary or string checking it against limits.
+    fn get_var_size(&mut self) -> Result<usize> {
+        let size = self.get_unsigned()?;
+        self.check_var_size(size)
+    }
+
+    /// Check size of binary or string, already read, against limits.
+    fn check_var_size(&self, size: u64) -> Result<usize> {
+        if size > self.max_var_len as u64 {
+            return Err(BipackError::LimitExceeded { size, limit: self.max_var_len });
+        }
+        let rest = (self.data.len() - self.position) as u64;
+        if self.strict && size > rest {
+            return Err(NoDataError { offset: self.position, needed: (size - rest) as usize });
+        }
+        Ok(size as usize)
+    }
+
+    pub(crate) fn skip(&mut self, size: usize) -> Result<()> {
+        let rest = self.data.len() - self.position;
+        if size > rest {
+            return Err(NoDataError { offset: self.position, needed: size - rest });
+        }
+        self.position += size;
+        Ok(())
     }
 }
 
 impl<'x> BipackSource for SliceSource<'x> {
     fn get_u8(self: &mut Self) -> Result<u8> {
         if self.position >= self.data.len() {
-            Err(NoDataError)
+            Err(NoDataError { offset: self.position, needed: 1 })
         } else {
             let result = self.data[self.position];
             self.position += 1;
             Ok(result)
         }
     }
+
+    fn get_u32(self: &mut Self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.get_exact()?))
+    }
+
+    fn get_u64(self: &mut Self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.get_exact()?))
+    }
+
+    fn get_fixed_bytes(self: &mut Self, size: usize) -> Result<Vec<u8>> {
+        let rest = self.data.len() - self.position;
+        if size > rest {
+            Err(NoDataError { offset: self.position, needed: size - rest })
+        } else {
+            let mut result = Vec::with_capacity(size);
+            result.extend_from_slice(&self.data[self.position..self.position + size]);
+            self.position += size;
+            Ok(result)
+        }
+    }
+
+    fn get_fixed_bytes_into(self: &mut Self, buf: &mut [u8]) -> Result<()> {
+        let rest = self.data.len() - self.position;
+        if buf.len() > rest {
+            Err(NoDataError { offset: self.position, needed: buf.len() - rest })
+        } else {
+            buf.copy_from_slice(&self.data[self.position..self.position + buf.len()]);
+            self.position += buf.len();
+            Ok(())
+        }
+    }
+
+    fn get_unsigned(self: &mut Self) -> Result<u64> {
+        if self.strict { self.get_unsigned_canonical() } else { read_unsigned(self) }
+    }
+
+    fn get_varint_unsigned(self: &mut Self) -> Result<u64> {
+        if self.strict { read_varint_strict(self) } else { read_varint(self) }
+    }
+
+    fn get_str_into(self: &mut Self, out: &mut String) -> Result<()> {
+        out.clear();
+        let size = self.get_var_size()?;
+        read_str_into(self, size, out)
+    }
+
+    fn get_var_bytes_into(self: &mut Self, out: &mut Vec<u8>) -> Result<usize> {
+        out.clear();
+        let size = self.get_var_size()?;
+        let rest = self.data.len() - self.position;
+        if size > rest {
+            return Err(NoDataError { offset: self.position, needed: size - rest });
+        }
+        out.extend_from_slice(&self.data[self.position..self.position + size]);
+        self.position += size;
+        Ok(size)
+    }
+
+    fn get_unsigned_counted(self: &mut Self) -> Result<(u64, usize)> {
+        let start = self.position;
+        let value = self.get_unsigned()?;
+        Ok((value, self.position - start))
+    }
+
+    fn get_var_bytes(self: &mut Self) -> Result<Vec<u8>> {
+        let size = self.get_var_size()?;
+        self.get_fixed_bytes(size)
+    }
+
+    fn get_opt_str(self: &mut Self) -> Result<Option<String>> {
+        match self.get_unsigned()? {
+            0 => Ok(None),
+            size => {
+                let size = self.check_var_size(size - 1)?;
+                String::from_utf8(self.get_fixed_bytes(size)?)
+                    .map(Some)
+                    .map_err(BipackError::BadEncoding)
+            }
+        }
+    }
+
+    fn get_rest(self: &mut Self) -> Result<Vec<u8>> {
+        Ok(self.get_rest_borrowed().to_vec())
+    }
+}
+
+fn read_unsigned<S: BipackSource + ?Sized>(source: &mut S) -> Result<u64> {
+    let first = source.get_u8()?;
+    let mut result = (first >> 2) as u64;
+    match first & 3 {
+        0 => Ok(result),
+        1 => Ok(result | (source.get_u8()? as u64) << 6),
+        ty => {
+            result |= (source.get_u8()? as u64) << 6;
+            result |= (source.get_u8()? as u64) << 14;
+            if ty == 2 { return Ok(result); }
+            Ok(result | (source.get_varint_unsigned()? << 22))
+        }
+    }
+}
+
+fn read_varint<S: BipackSource + ?Sized>(source: &mut S) -> Result<u64> {
+    let mut result = 0u64;
+    let mut count = 0;
+    loop {
+        let x = source.get_u8()? as u64;
+        result |= (x & 0x7F).checked_shl(count).unwrap_or(0);
+        if (x & 0x80) == 0 { return Ok(result); }
+        count += 7
+    }
+}
+
+/// Read varint rejecting values that do not fit u64 with [BipackError::Overflow] and
+/// trailing zero bytes with [BipackError::NonCanonical].
+fn read_varint_strict<S: BipackSource + ?Sized>(source: &mut S) -> Result<u64> {
+    let mut result = 0u64;
+    let mut count = 0;
+    loop {
+        let x = source.get_u8()? as u64;
+        if count > 63 || (count == 63 && (x & 0x7F) > 1) { return Err(BipackError::Overflow); }
+        result |= (x & 0x7F) << count;
+        if (x & 0x80) == 0 {
+            if x == 0 && count > 0 { return Err(BipackError::NonCanonical); }
+            return Ok(result);
+        }
+        count += 7
+    }
+}
+
+/// Append `size` bytes to `out`, growing it by 4096 bytes at most at a time, so a wrong
+/// size fails when the data end instead of causing a huge allocation. Truncates `out` back
+/// on error.
+fn read_bytes_into<S: BipackSource + ?Sized>(source: &mut S, size: usize, out: &mut Vec<u8>) -> Result<()> {
+    let base = out.len();
+    while out.len() - base < size {
+        let start = out.len();
+        out.resize(start + (size - (start - base)).min(4096), 0);
+        if let Err(e) = source.get_fixed_bytes_into(&mut out[start..]) {
+            out.truncate(base);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Read `size` bytes of utf8 into `out` reusing its buffer, clearing it on error.
+fn read_str_into<S: BipackSource + ?Sized>(source: &mut S, size: usize, out: &mut String) -> Result<()> {
+    let mut buffer = core::mem::take(out).into_bytes();
+    buffer.clear();
+    if let Err(e) = read_bytes_into(source, size, &mut buffer) {
+        *out = String::from_utf8(buffer).unwrap_or_default();
+        return Err(e);
+    }
+    match String::from_utf8(buffer) {
+        Ok(s) => {
+            *out = s;
+            Ok(())
+        }
+        Err(e) => {
+            let error = e.clone();
+            let mut buffer = e.into_bytes();
+            buffer.clear();
+            *out = String::from_utf8(buffer).unwrap_or_default();
+            Err(BipackError::BadEncoding(error))
+        }
+    }
+}
+
+/// Source that counts bytes read from the underlying one.
+struct CountingSource<'a, S: BipackSource + ?Sized> {
+    source: &'a mut S,
+    count: usize,
+}
+
+impl<'a, S: BipackSource + ?Sized> BipackSource for CountingSource<'a, S> {
+    fn get_u8(self: &mut Self) -> Result<u8> {
+        let result = self.source.get_u8()?;
+        self.count += 1;
+        Ok(result)
+    }
+}
+
+/// Source that can return binaries borrowed from the underlying buffer that outlives it,
+/// like the slice of [SliceSource] or a memory-mapped file, to decode without copying.
+///
+/// The lifetime `'a` is the one of the buffer, not of the source: returned slices remain
+/// valid after the source is dropped, so the buffer must not change while borrowed.
+pub trait BorrowingSource<'a>: BipackSource {
+    /// Read variable-length binary like [BipackSource::get_var_bytes] but without copying.
+    fn get_var_bytes_ref(self: &mut Self) -> Result<&'a [u8]>;
+
+    /// Read string like [BipackSource::get_str] but without copying.
+    fn get_str_ref(self: &mut Self) -> Result<&'a str> {
+        let bytes = self.get_var_bytes_ref()?;
+        core::str::from_utf8(bytes).map_err(|_| {
+            BipackError::BadEncoding(String::from_utf8(bytes.to_vec()).unwrap_err())
+        })
+    }
+}
+
+impl<'a> BorrowingSource<'a> for SliceSource<'a> {
+    fn get_var_bytes_ref(self: &mut Self) -> Result<&'a [u8]> {
+        let size = self.get_var_size()?;
+        let rest = self.data.len() - self.position;
+        if size > rest {
+            return Err(NoDataError { offset: self.position, needed: size - rest });
+        }
+        let data: &'a [u8] = self.data;
+        self.position += size;
+        Ok(&data[self.position - size..self.position])
+    }
+}
+
+/// Iterator over bytes of the [SliceSource], see [SliceSource::bytes].
+pub struct SliceBytes<'s, 'a> {
+    source: &'s mut SliceSource<'a>,
+}
+
+impl<'s, 'a> Iterator for SliceBytes<'s, 'a> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        self.source.get_u8().ok()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let rest = self.source.data.len() - self.source.position;
+        (rest, Some(rest))
+    }
+}
+
+/// Source wrapper that reports which field failed to decode: wrap decoding of fields with
+/// [TracingSource::named], and errors come as [BipackError::AtPath] with the labels of
+/// all enclosing fields, like `header.version`.
+pub struct TracingSource<S: BipackSource> {
+    source: S,
+}
+
+impl<S: BipackSource> TracingSource<S> {
+    pub fn new(source: S) -> TracingSource<S> { TracingSource { source } }
+
+    pub fn into_inner(self) -> S { self.source }
+
+    /// Decode a field with `f`, adding `label` to the error path if it fails.
+    pub fn named<T>(&mut self, label: &str, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        f(self).map_err(|e| match e {
+            BipackError::AtPath { mut path, error } => {
+                path.insert(0, label.to_string());
+                BipackError::AtPath { path, error }
+            }
+            e => BipackError::AtPath { path: alloc::vec![label.to_string()], error: Box::new(e) },
+        })
+    }
+}
+
+/// Source wrapper to unpack strings packed with
+/// [crate::bipack_sink::DictSink::put_interned_str], rebuilding the dictionary as it goes.
+/// Other values are read from the wrapped source as is.
+pub struct DictSource<S: BipackSource> {
+    source: S,
+    dictionary: Vec<String>,
+}
+
+impl<S: BipackSource> DictSource<S> {
+    pub fn new(source: S) -> DictSource<S> { DictSource { source, dictionary: Vec::new() } }
+
+    pub fn into_inner(self) -> S { self.source }
+
+    /// Read interned string. Index of a string not yet seen causes
+    /// [BipackError::InvalidValue].
+    pub fn get_interned_str(&mut self) -> Result<String> {
+        let index = self.source.get_unsigned()?;
+        if let Some(str) = self.dictionary.get(index as usize) { return Ok(str.clone()); }
+        if index != self.dictionary.len() as u64 { return Err(BipackError::InvalidValue); }
+        let str = self.source.get_str()?;
+        self.dictionary.push(str.clone());
+        Ok(str)
+    }
+}
+
+/// Implement [BipackSource] for a wrapper with the `source` field by forwarding to it, so
+/// its limits and bulk reads apply through the wrapper.
+macro_rules! forward_source {
+    ($($wrapper: ident),*) => {
+        $(impl<S: BipackSource> BipackSource for $wrapper<S> {
+            fn get_u8(self: &mut Self) -> Result<u8> {
+                self.source.get_u8()
+            }
+
+            fn get_str_into(self: &mut Self, out: &mut String) -> Result<()> {
+                out.clear();
+                self.source.get_str_into(out)
+            }
+
+            fn get_var_bytes_into(self: &mut Self, out: &mut Vec<u8>) -> Result<usize> {
+                self.source.get_var_bytes_into(out)
+            }
+
+            fn get_fixed_bytes_into(self: &mut Self, buf: &mut [u8]) -> Result<()> {
+                self.source.get_fixed_bytes_into(buf)
+            }
+
+            fn get_fixed_bytes(self: &mut Self, size: usize) -> Result<Vec<u8>> {
+                self.source.get_fixed_bytes(size)
+            }
+
+            fn get_var_bytes(self: &mut Self) -> Result<Vec<u8>> {
+                self.source.get_var_bytes()
+            }
+
+            fn get_opt_str(self: &mut Self) -> Result<Option<String>> {
+                self.source.get_opt_str()
+            }
+
+            fn get_unsigned(self: &mut Self) -> Result<u64> {
+                self.source.get_unsigned()
+            }
+        })*
+    };
+}
+
+forward_source!(TracingSource, DictSource);
+
+/// Reads values packed as bit fields, most significant bit first, from any source.
+/// Unused bits of the last partially read byte are dropped with the reader.
+pub struct BitReader<'a, S: BipackSource + ?Sized> {
+    source: &'a mut S,
+    current: u8,
+    available: u32,
+}
+
+impl<'a, S: BipackSource + ?Sized> BitReader<'a, S> {
+    pub fn new(source: &'a mut S) -> BitReader<'a, S> {
+        BitReader { source, current: 0, available: 0 }
+    }
+
+    /// Read `n` bits, up to 64, as unsigned value.
+    pub fn get_bits(&mut self, n: u32) -> Result<u64> {
+        assert!(n <= 64, "can't read more than 64 bits at once");
+        let mut result = 0u64;
+        for _ in 0..n {
+            if self.available == 0 {
+                self.current = self.source.get_u8()?;
+                self.available = 8;
+            }
+            self.available -= 1;
+            result = (result << 1) | ((self.current >> self.available) & 1) as u64;
+        }
+        Ok(result)
+    }
+
+    /// Read `n` bits, up to 64, as two's complement signed value, e.g. sign-extending
+    /// it from bit `n-1`. For example, 12 bits all set are read as -1.
+    pub fn get_bits_signed(&mut self, n: u32) -> Result<i64> {
+        let value = self.get_bits(n)?;
+        if n == 0 { return Ok(0); }
+        let shift = 64 - n;
+        Ok(((value << shift) as i64) >> shift)
+    }
+}
+
+/// Iterator over back-to-back messages each prefixed with its smartint-encoded byte length,
+/// as written by [crate::bipack_sink::BipackSink::put_var_bytes]. Yields message bodies as
+/// subslices of the source data without copying, stops at the end of data. A malformed or
+/// truncated trailing frame yields one error and then iteration stops.
+pub struct MessageIter<'a> {
+    source: SliceSource<'a>,
+    alignment: usize,
+    failed: bool,
+}
+
+impl<'a> MessageIter<'a> {
+    pub fn from(src: &'a [u8]) -> MessageIter<'a> {
+        MessageIter { source: SliceSource::from(src), alignment: 1, failed: false }
+    }
+
+    /// Iterate records written with [crate::bipack_sink::VecSink::put_aligned_record],
+    /// skipping padding between them.
+    pub fn aligned(src: &'a [u8]) -> MessageIter<'a> {
+        MessageIter { alignment: RECORD_ALIGNMENT, ..MessageIter::from(src) }
+    }
+
+    fn next_frame(self: &mut Self) -> Result<&'a [u8]> {
+        let size = self.source.get_unsigned()? as usize;
+        let start = self.source.position;
+        let rest = self.source.data.len() - start;
+        if size > rest {
+            return Err(NoDataError { offset: start, needed: size - rest });
+        }
+        self.source.position = (start + size).next_multiple_of(self.alignment)
+            .min(self.source.data.len());
+        Ok(&self.source.data[start..start + size])
+    }
 }
 
+impl<'a> Iterator for MessageIter<'a> {
+    type Item = Result<&'a [u8]>;
 
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.source.position >= self.source.data.len() {
+            return None;
+        }
+        let result = self.next_frame();
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
@@ -0,0 +1,83 @@
+// Copyright 2023 by Sergey S. Chernov.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+
+use bytes::Bytes;
+
+use crate::bipack_source::BipackError::NoDataError;
+use crate::bipack_source::{BipackSource, Result};
+
+/// The bipack source over [Bytes] that can return variable-length binaries as [Bytes]
+/// sharing the same buffer, without copying, see [BytesSource::get_var_bytes_shared].
+/// Available with `bytes` feature.
+pub struct BytesSource {
+    data: Bytes,
+    position: usize,
+}
+
+impl BytesSource {
+    pub fn from(src: Bytes) -> BytesSource {
+        BytesSource { data: src, position: 0 }
+    }
+
+    /// Current read position, e.g. number of bytes already consumed.
+    pub fn position(&self) -> usize { self.position }
+
+    /// Read variable-length binary like [BipackSource::get_var_bytes], but return it as a
+    /// slice of the source buffer, without copying.
+    pub fn get_var_bytes_shared(&mut self) -> Result<Bytes> {
+        let size = self.get_unsigned()? as usize;
+        let rest = self.data.len() - self.position;
+        if size > rest {
+            return Err(NoDataError { offset: self.position, needed: size - rest });
+        }
+        let result = self.data.slice(self.position..self.position + size);
+        self.position += size;
+        Ok(result)
+    }
+}
+
+impl BipackSource for BytesSource {
+    fn get_u8(self: &mut Self) -> Result<u8> {
+        if self.position >= self.data.len() {
+            Err(NoDataError { offset: self.position, needed: 1 })
+        } else {
+            let result = self.data[self.position];
+            self.position += 1;
+            Ok(result)
+        }
+    }
+
+    fn get_fixed_bytes(self: &mut Self, size: usize) -> Result<Vec<u8>> {
+        let rest = self.data.len() - self.position;
+        if size > rest {
+            return Err(NoDataError { offset: self.position, needed: size - rest });
+        }
+        let result = self.data[self.position..self.position + size].to_vec();
+        self.position += size;
+        Ok(result)
+    }
+
+    fn get_fixed_bytes_into(self: &mut Self, buf: &mut [u8]) -> Result<()> {
+        let rest = self.data.len() - self.position;
+        if buf.len() > rest {
+            Err(NoDataError { offset: self.position, needed: buf.len() - rest })
+        } else {
+            buf.copy_from_slice(&self.data[self.position..self.position + buf.len()]);
+            self.position += buf.len();
+            Ok(())
+        }
+    }
+}
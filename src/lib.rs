@@ -26,16 +26,66 @@
 //!   into the bipack format. It is the same simple to implement it for any else binary data
 //!   source.
 //!
+//! - [bipack::BiPackable] and [bipack::BiUnpackable] are (experimental) traits to pack and unpack
+//!   whole values, used by the [bipack!] macro.
+//!
+//! - with `testing` feature, `testing` module provides property checks to
+//!   test custom sinks and sources against the reference implementation.
+//!
+//! - with `bytes` feature, `bytes_source::BytesSource` decodes from `bytes::Bytes`, returning
+//!   binaries that share its buffer.
+//!
+//! - with `flate2` feature, `put_var_bytes_compressed` deflates big binaries when it makes
+//!   them shorter.
+//!
+//! - with `smallvec` feature, `get_smallvec` decodes short sequences without heap allocation.
+//!
+//! - with `tokio` feature, `async_source::AsyncReadSource` decodes from tokio `AsyncRead` and
+//!   `async_sink::AsyncWriteSink` encodes to `AsyncWrite`.
+//!
+//! - with `wasm` feature, `wasm_codec::WasmCodec` exposes packing of integers, strings and
+//!   binaries to JS host code with `wasm-bindgen`.
+//!
+//! - [try_sink::TryBipackSink] is a fallible sink for bounded buffers where overflow must be
+//!   reported, e.g. [try_sink::SliceSink] writing to a fixed slice.
+//!
+//! ## `no_std` support
+//!
+//! The crate only needs `alloc` for [`Vec`] and [`String`]. The `std` feature is on by default;
+//! disable default features to build it for `#![no_std]` targets like smart contracts. Without
+//! `std` the [bipack_source::BipackError] still implements `Display` and `Debug`, but not
+//! `std::error::Error`.
+//!
 //! ## Utilities
 //!
 //! - to siplify encoding of unsigned ints the [bipack_sink::IntoU64] trait is used with
-//!   imlementation for usual u* types.
+//!   imlementation for usual u* types. Use [bipack_unsigned!] to make own types implementing
+//!   it [bipack::BiPackable].
 //!
 //! - [tools::to_dump] utility function converts binary data into human-readable dump as in old goot
-//!   times (address, bytes, ASCII characters).
+//!   times (address, bytes, ASCII characters), [tools::to_dump_highlight] also marks a byte range
+//!   in it, e.g. where decoding failed. `tools::dump_to_io` writes the same dump to
+//!   `std::io::Write`, e.g. stdout, without building the whole string.
 //!
 //! - [tools::StringBuilder] minimalistic growing strings builder.
 //!
+//! - [tools::to_hex] and [tools::from_hex] convert packed data to and from hex strings.
+//!
+//! - [tools::to_compact] and [tools::from_compact] convert packed data to and from URL-safe
+//!   base64 text without padding, with no extra dependencies.
+//!
+//! - [tools::seal] appends CRC32 of the whole message, [tools::unseal] checks and strips it.
+//!
+//! - with `base64` feature, `tools::to_base64` and `tools::from_base64` convert packed data
+//!   to and from text.
+//!
+//! - [schema::Schema] describes packed message layout to walk it without decoding, e.g.
+//!   [schema::is_canonical] verifies the message is canonically encoded before checking
+//!   its signature.
+//!
+//! - [pull_decoder::PullDecoder] decodes a message described by schema from bytes fed as
+//!   they arrive, reporting fields as events.
+//!
 //! ## About Bipack format
 //!
 //! This is a binary format created wround the idea of bit-effectiveness and not disclosing
@@ -102,32 +152,63 @@
 //! | type | encoded |
 //! |------|---------|
 //! | 0 | v0 is the result 0..64 (or -32..32) |
-//! | 1 | v0 ## v1 are the result, 14 bits |
-//! | 2 | v0  ## v1 ## v2 are the result, 22bits
-//! | 3 | v0, ## v1 ## v2 ## (varint encoded rest) |
+//! | 1 | v1 ## v0 are the result, 14 bits |
+//! | 2 | v2 ## v1 ## v0 are the result, 22bits
+//! | 3 | (varint encoded rest) ## v2 ## v1 ## v0 |
 //!
-//! Where `##` means bits concatenation. The bits are interpreted as BIG ENDIAN,
-//! for example `24573` will be encoded to `EA FF 02`
+//! Where `##` means bits concatenation, so v0 holds the lowest 6 bits of the value, v1 the
+//! next 8 bits and so on, LITTLE ENDIAN. For example `24573` (`0x5FFD`) will be encoded to
+//! `F6 7F 01`: type 2 with v0 = `0x3D`, v1 = `0x7F`, v2 = `0x01`.
 //!
 //!
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![allow(dead_code)]
 #![allow(unused_variables)]
+#![allow(clippy::needless_arbitrary_self_type)]
+
+extern crate alloc;
 
 pub mod bipack_source;
 pub mod bipack_sink;
+pub mod try_sink;
 pub mod tools;
-mod bipack;
+pub mod schema;
+pub mod pull_decoder;
+pub mod bipack;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "bytes")]
+pub mod bytes_source;
+#[cfg(feature = "tokio")]
+pub mod async_source;
+#[cfg(feature = "tokio")]
+pub mod async_sink;
+#[cfg(feature = "wasm")]
+pub mod wasm_codec;
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
     use base64::Engine;
 
-    use crate::bipack;
-    use crate::bipack::{BiPackable, BiUnpackable};
-    use crate::bipack_sink::BipackSink;
-    use crate::bipack_source::{BipackSource, Result, SliceSource};
-    use crate::tools::to_dump;
+    use crate::{bipack, bipack_enum, bipack_into, bipack_unsigned};
+    use crate::bipack::{from_slice_exact, pack_delta_chain, unpack_delta_chain, BiDelta, BiPackable, BiUnpackable, EnumCodec, RawVariant};
+    use crate::bipack_sink::{BipackSink, DictSink, IoSink, NegativeValue, SizeSink, VecSink, V0LIMIT, V1LIMIT, V2LIMIT};
+    use crate::schema::{decode_cost, is_canonical, Field, Schema, FIELD_COST};
+    use crate::bipack_source::{BipackError, BipackSource, BitReader, BorrowingSource, DictSource, MessageIter, Result, SliceSource, TracingSource};
+    use crate::pull_decoder::{Event, PullDecoder};
+    use crate::tools::{crc32, describe, dump_to_io, from_compact, from_hex, seal, to_compact, to_dump, to_dump_highlight, to_hex, unseal};
+    use crate::try_sink::{SinkFull, SliceSink, TryBipackSink};
+
+    /// Smartint encoding of the value as hex, to check examples from the docs.
+    fn encode_unsigned_hex(value: u64) -> String {
+        let mut data = Vec::new();
+        data.put_unsigned(value);
+        hex::encode(data)
+    }
 
     #[test]
     fn fixed_unpack() -> Result<()> {
@@ -169,6 +250,22 @@ mod tests {
         assert_eq!("07fa00000101d0000000d8cb80a02f", hex::encode(&data));
     }
 
+    #[test]
+    fn no_data_error_offset() {
+        let data = [1u8, 2, 3];
+        let mut ss = SliceSource::from(&data);
+        assert_eq!(1, ss.get_u8().unwrap());
+        match ss.get_u32() {
+            Err(BipackError::NoDataError { offset, needed }) => {
+                assert_eq!(1, offset);
+                assert_eq!(2, needed);
+            }
+            x => panic!("unexpected result: {:?}", x),
+        }
+        let e = SliceSource::from(&data[..0]).get_u8().unwrap_err();
+        assert_eq!("not enough data at offset 0: 1 more byte(s) needed", e.to_string());
+    }
+
     #[test]
     fn smart_pack() {
         let mut data: Vec<u8> = Vec::new();
@@ -268,6 +365,13 @@ mod tests {
         test2(256)?;
         test2(2147483647)?;
         test2(2222147483647)?;
+        test2(i64::MAX)?;
+
+        let mut x = Vec::new();
+        assert!(matches!(x.put_signed_checked(i64::MIN), Err(BipackError::Overflow)));
+        assert!(x.is_empty());
+        x.put_signed_checked(-5)?;
+        assert_eq!(-5, SliceSource::from(&x).get_signed()?);
         Ok(())
     }
 
@@ -284,4 +388,2119 @@ mod tests {
         assert_eq!("hello!", s1);
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_shared_header() -> Result<()> {
+        use crate::bipack::{pack_with_header, unpack_with_header, VariantCodec};
+
+        #[derive(Debug, PartialEq)]
+        enum Event {
+            Login { user: String },
+            Transfer { amount: u64, to: String },
+        }
+        impl VariantCodec for Event {
+            fn tag(self: &Self) -> u64 {
+                match self {
+                    Event::Login { .. } => 0,
+                    Event::Transfer { .. } => 1,
+                }
+            }
+
+            fn pack_body(self: &Self, sink: &mut impl BipackSink) {
+                match self {
+                    Event::Login { user } => sink.put_str(user),
+                    Event::Transfer { amount, to } => {
+                        sink.put_unsigned(*amount);
+                        sink.put_str(to);
+                    }
+                }
+            }
+
+            fn unpack_body(tag: u64, source: &mut impl BipackSource) -> Result<Event> {
+                match tag {
+                    0 => Ok(Event::Login { user: source.get_str()? }),
+                    1 => Ok(Event::Transfer { amount: source.get_unsigned()?, to: source.get_str()? }),
+                    _ => Err(BipackError::UnknownVariant(tag)),
+                }
+            }
+        }
+        let timestamp = 1_700_000_000u64;
+        let events = [Event::Login { user: "alice".to_string() },
+            Event::Transfer { amount: 500, to: "bob".to_string() }];
+        for event in events {
+            let mut data = Vec::new();
+            pack_with_header(&timestamp, &event, &mut data);
+            // the shared field comes first, before the tag, for every variant
+            let mut src = SliceSource::from(&data);
+            assert_eq!(timestamp, src.get_unsigned()?);
+            assert_eq!(event.tag(), src.get_unsigned()?);
+
+            let mut src = SliceSource::from(&data);
+            assert_eq!((timestamp, event), unpack_with_header::<u64, Event>(&mut src)?);
+        }
+
+        let mut data = Vec::new();
+        data.put_unsigned(timestamp);
+        data.put_unsigned(7u8);
+        let unknown = unpack_with_header::<u64, Event>(&mut SliceSource::from(&data));
+        assert!(matches!(unknown, Err(BipackError::UnknownVariant(7))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_histogram() -> Result<()> {
+        let buckets = [(10u64, 3u64), (11, 1), (500, 0), (70000, 12345), (u64::MAX, 7)];
+        let mut h1 = BTreeMap::new();
+        for (k, v) in buckets { h1.insert(k, v); }
+        let mut h2 = BTreeMap::new();
+        for (k, v) in buckets.iter().rev() { h2.insert(*k, *v); }
+        let mut d1 = Vec::new();
+        d1.put_histogram(&h1);
+        let mut d2 = Vec::new();
+        d2.put_histogram(&h2);
+        assert_eq!(d1, d2);
+        assert_eq!(h1, SliceSource::from(&d1).get_histogram()?);
+
+        let mut bad = Vec::new();
+        bad.put_unsigned(2u8);
+        for delta in [u64::MAX, 1] {
+            bad.put_unsigned(delta);
+            bad.put_unsigned(1u8);
+        }
+        assert!(matches!(SliceSource::from(&bad).get_histogram(), Err(BipackError::Overflow)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fixed_bytes_into() -> Result<()> {
+        let data = [1u8, 2, 3, 4, 5];
+        let mut ss = SliceSource::from(&data);
+        let mut buf = [0u8; 3];
+        ss.get_fixed_bytes_into(&mut buf)?;
+        assert_eq!([1, 2, 3], buf);
+        assert!(matches!(ss.get_fixed_bytes_into(&mut buf),
+            Err(BipackError::NoDataError { offset: 3, needed: 1 })));
+        let mut buf = [0u8; 2];
+        ss.get_fixed_bytes_into(&mut buf)?;
+        assert_eq!([4, 5], buf);
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_iter() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_var_bytes(b"first");
+        data.put_var_bytes(b"");
+        data.put_var_bytes(&[7u8; 100]);
+        let frames = MessageIter::from(&data).collect::<Result<Vec<_>>>()?;
+        assert_eq!(vec![&b"first"[..], &b""[..], &[7u8; 100][..]], frames);
+
+        data.put_unsigned(10u8);
+        data.put_fixed_bytes(b"abc");
+        let mut it = MessageIter::from(&data);
+        assert_eq!(b"first", it.next().unwrap()?);
+        it.next();
+        it.next();
+        assert!(matches!(it.next(), Some(Err(BipackError::NoDataError { needed: 7, .. }))));
+        assert!(it.next().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_canonical_message() -> Result<()> {
+        let schema = Schema(vec![
+            Field::FixedU16,
+            Field::SmartUint,
+            Field::SmartInt,
+            Field::Str,
+            Field::SortedArray(Box::new(Field::SmartUint)),
+        ]);
+        let mut data = Vec::new();
+        data.put_u16(17);
+        data.put_unsigned(5000000u32);
+        data.put_signed(-12);
+        data.put_str("signed");
+        data.put_unsigned(3u8);
+        for x in [1u32, 100, 70000] { data.put_unsigned(x); }
+        assert!(is_canonical(&data, &schema)?);
+
+        // trailing data
+        let mut bad = data.clone();
+        bad.push(0);
+        assert!(!is_canonical(&bad, &schema)?);
+
+        // 5 packed as type 1 smartint instead of a single byte
+        let mut bad = Vec::new();
+        bad.put_u16(17);
+        bad.put_fixed_bytes(&[(5 << 2) | 1, 0]);
+        bad.extend_from_slice(&data[6..]);
+        assert!(!is_canonical(&bad, &schema)?);
+
+        // unsorted array
+        let mut bad = data[..data.len() - 6].to_vec();
+        for x in [100u32, 1, 70000] { bad.put_unsigned(x); }
+        assert!(!is_canonical(&bad, &schema)?);
+
+        assert!(is_canonical(&data[..data.len() - 1], &schema).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_canonical_sorted_numbers() -> Result<()> {
+        // 64 packs as 01 01 and 2 as 08, so packed bytes are not in numeric order
+        let set: std::collections::BTreeSet<u64> = [2, 64, 1000, 1 << 30, u64::MAX].into();
+        let values: Vec<u64> = set.into_iter().collect();
+        let mut data = Vec::new();
+        data.put_unsigned_slice(&values);
+        let schema = Schema(vec![Field::SortedArray(Box::new(Field::SmartUint))]);
+        assert!(is_canonical(&data, &schema)?);
+        assert!(is_canonical(&bipack!(values), &schema)?);
+
+        let mut signed = Vec::new();
+        signed.put_unsigned(4u8);
+        for x in [-100i64, -1, 0, 5] { signed.put_signed(x); }
+        assert!(is_canonical(&signed, &Schema(vec![Field::SortedArray(Box::new(Field::SmartInt))]))?);
+
+        let mut bad = Vec::new();
+        bad.put_unsigned_slice(&[64u64, 2]);
+        assert!(!is_canonical(&bad, &schema)?);
+        let mut duplicate = Vec::new();
+        duplicate.put_unsigned_slice(&[2u64, 2]);
+        assert!(!is_canonical(&duplicate, &schema)?);
+
+        // strings are still ordered by packed bytes
+        let strings = bipack!(vec!["a".to_string(), "b".to_string()]);
+        assert!(is_canonical(&strings, &Schema(vec![Field::SortedArray(Box::new(Field::Str))]))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_f32_matrix() -> Result<()> {
+        let m = [1.0f32, -2.5, f32::NAN, 0.0, f32::MAX, 1e-10];
+        let mut data = Vec::new();
+        data.put_f32_matrix(2, 3, &m)?;
+        assert_eq!(2 + 6 * 4, data.len());
+        let (rows, cols, m1) = SliceSource::from(&data).get_f32_matrix()?;
+        assert_eq!((2, 3), (rows, cols));
+        assert_eq!(m.map(f32::to_bits).to_vec(), m1.iter().map(|x| x.to_bits()).collect::<Vec<_>>());
+        assert!(m1[2].is_nan());
+
+        let mut bad = Vec::new();
+        assert!(matches!(bad.put_f32_matrix(2, 2, &m), Err(BipackError::InvalidValue)));
+        assert!(matches!(bad.put_f32_matrix(usize::MAX, 2, &m), Err(BipackError::Overflow)));
+        assert!(bad.is_empty());
+        bad.put_unsigned(usize::MAX);
+        bad.put_unsigned(2u8);
+        assert!(matches!(SliceSource::from(&bad).get_f32_matrix(), Err(BipackError::Overflow)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_vec_sink() {
+        let mut sink = VecSink::with_capacity(64);
+        assert!(sink.capacity() >= 64);
+        sink.put_u8(7);
+        sink.put_str("hello");
+        assert_eq!(7, sink.len());
+        assert_eq!("071468656c6c6f", hex::encode(sink.into_inner()));
+        assert!(VecSink::default().is_empty());
+    }
+
+    #[test]
+    fn test_aligned_records() -> Result<()> {
+        let mut sink = VecSink::new();
+        let mut offsets = Vec::new();
+        for i in 0..4usize {
+            sink.put_aligned_record(|r| {
+                r.put_unsigned(i);
+                r.put_str(&"x".repeat(i * 5));
+            });
+            offsets.push(sink.len());
+        }
+        assert!(offsets.iter().all(|x| x % 8 == 0));
+        let data = sink.into_inner();
+        let mut count = 0;
+        for (i, record) in MessageIter::aligned(&data).enumerate() {
+            let mut src = SliceSource::from(record?);
+            assert_eq!(i as u64, src.get_unsigned()?);
+            assert_eq!("x".repeat(i * 5), src.get_str()?);
+            count += 1;
+        }
+        assert_eq!(4, count);
+        Ok(())
+    }
+
+    #[test]
+    fn test_io_sink() {
+        let text = "bulk ".repeat(1000);
+        let mut sink = IoSink::new(std::io::Cursor::new(Vec::new()));
+        sink.put_u16(0x1234);
+        sink.put_str(&text);
+        let mut data = Vec::new();
+        data.put_u16(0x1234);
+        data.put_str(&text);
+        assert_eq!(data, sink.into_inner().unwrap().into_inner());
+
+        let mut buf = [0u8; 4];
+        let mut sink = IoSink::new(&mut buf[..]);
+        sink.put_str("too long");
+        assert!(sink.error().is_some());
+    }
+
+    #[test]
+    fn test_crc_block() -> Result<()> {
+        assert_eq!(0xCBF43926, crc32(b"123456789"));
+        let mut data = Vec::new();
+        data.put_crc_block(|body| {
+            body.put_unsigned(42u8);
+            body.put_str("payload");
+        });
+        let body = SliceSource::from(&data).get_crc_block()?;
+        let mut src = SliceSource::from(&body);
+        assert_eq!(42, src.get_unsigned()?);
+        assert_eq!("payload", src.get_str()?);
+
+        data[3] ^= 1;
+        assert!(matches!(SliceSource::from(&data).get_crc_block(), Err(BipackError::ChecksumMismatch)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_variant() -> Result<()> {
+        #[derive(Debug, PartialEq)]
+        enum Shape {
+            Circle(u32),
+            Unknown(RawVariant),
+        }
+        fn unpack(source: &mut SliceSource) -> Result<Shape> {
+            let (tag, body) = source.get_variant_body()?;
+            Ok(match tag {
+                0 => Shape::Circle(SliceSource::from(&body).get_unsigned()? as u32),
+                _ => Shape::Unknown(RawVariant { tag, body }),
+            })
+        }
+
+        let mut data = Vec::new();
+        data.put_variant_body(0, |b| b.put_unsigned(10u8));
+        // variant from a newer version
+        data.put_variant_body(7, |b| {
+            b.put_str("square");
+            b.put_u32(12);
+        });
+        let mut src = SliceSource::from(&data);
+        assert_eq!(Shape::Circle(10), unpack(&mut src)?);
+        let Shape::Unknown(raw) = unpack(&mut src)? else { panic!("must be unknown") };
+        assert_eq!(7, raw.tag);
+        assert_eq!(&data[3..], bipack!(raw).as_slice());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsigned_canonical() -> Result<()> {
+        for x in [0u64, 63, 64, 16383, 16384, 4194303, 4194304, 1 << 40, u64::MAX] {
+            let mut data = Vec::new();
+            data.put_unsigned(x);
+            assert_eq!(x, SliceSource::from(&data).get_unsigned_canonical()?);
+        }
+        // 5 as type 1, 100 as type 2, 200 as type 3 and 2^22 with redundant varint byte
+        for bad in [&[0x15u8, 0][..], &[0x92, 1, 0], &[0x23, 3, 0, 0], &[3, 0, 0, 0x81, 0]] {
+            assert!(matches!(SliceSource::from(bad).get_unsigned_canonical(),
+                Err(BipackError::NonCanonical)));
+        }
+        assert_eq!(5, SliceSource::from(&[0x15u8, 0]).get_unsigned()?);
+        assert_eq!(1 << 22, SliceSource::from(&[3u8, 0, 0, 0x81, 0]).get_unsigned()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bits_signed() -> Result<()> {
+        let data = [0xFFu8, 0xF5, 0x80, 0x07];
+        let mut src = SliceSource::from(&data);
+        let mut bits = BitReader::new(&mut src);
+        assert_eq!(-1, bits.get_bits_signed(12)?);
+        assert_eq!(5, bits.get_bits(4)?);
+        assert_eq!(-2048, bits.get_bits_signed(12)?);
+        assert_eq!(7, bits.get_bits_signed(4)?);
+        assert!(bits.get_bits(1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_delta_chain() -> Result<()> {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Quote {
+            time: u64,
+            bid: u32,
+            ask: u32,
+        }
+        impl BiPackable for Quote {
+            fn bi_pack(self: &Self, sink: &mut impl BipackSink) {
+                sink.put_unsigned(self.time);
+                sink.put_unsigned(self.bid);
+                sink.put_unsigned(self.ask);
+            }
+        }
+        impl BiUnpackable for Quote {
+            fn bi_unpack(source: &mut impl BipackSource) -> Result<Quote> {
+                Ok(Quote {
+                    time: source.get_unsigned()?,
+                    bid: source.get_packed_u32()?,
+                    ask: source.get_packed_u32()?,
+                })
+            }
+        }
+        impl BiDelta for Quote {
+            fn bi_pack_delta(self: &Self, previous: &Self, sink: &mut impl BipackSink) {
+                let mask = (self.time != previous.time) as u8
+                    | ((self.bid != previous.bid) as u8) << 1
+                    | ((self.ask != previous.ask) as u8) << 2;
+                sink.put_u8(mask);
+                if mask & 1 != 0 { sink.put_unsigned(self.time); }
+                if mask & 2 != 0 { sink.put_unsigned(self.bid); }
+                if mask & 4 != 0 { sink.put_unsigned(self.ask); }
+            }
+
+            fn bi_unpack_delta(previous: &Self, source: &mut impl BipackSource) -> Result<Self> {
+                let mask = source.get_u8()?;
+                let mut result = previous.clone();
+                if mask & 1 != 0 { result.time = source.get_unsigned()?; }
+                if mask & 2 != 0 { result.bid = source.get_packed_u32()?; }
+                if mask & 4 != 0 { result.ask = source.get_packed_u32()?; }
+                Ok(result)
+            }
+        }
+
+        let q1 = Quote { time: 1700000000, bid: 10000, ask: 10010 };
+        let q2 = Quote { bid: 10005, ..q1.clone() };
+        let q3 = Quote { ask: 10020, ..q2.clone() };
+        let records = vec![q1.clone(), q2, q3];
+        let mut data = Vec::new();
+        pack_delta_chain(&records, &mut data);
+        assert_eq!(1 + bipack!(q1).len() + 2 * 3, data.len());
+        assert_eq!(records, unpack_delta_chain::<Quote>(&mut SliceSource::from(&data))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_arrays() -> Result<()> {
+        let mut hash = [0u8; 32];
+        for (i, x) in hash.iter_mut().enumerate() { *x = (i * 7) as u8; }
+        let numbers = [1u32, 1000, 100000];
+        let data = bipack!(hash, numbers);
+        assert_eq!(32 + 1 + 2 + 3, data.len());
+        assert_eq!(&hash, &data[..32]);
+        let mut src = SliceSource::from(&data);
+        assert_eq!(hash, <[u8; 32]>::bi_unpack(&mut src)?);
+        assert_eq!(numbers, <[u32; 3]>::bi_unpack(&mut src)?);
+        assert!(<[u8; 2]>::bi_unpack(&mut src).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_cost() -> Result<()> {
+        let schema = Schema(vec![
+            Field::FixedU32,
+            Field::Str,
+            Field::Array(Box::new(Field::SmartInt)),
+        ]);
+        let mut data = Vec::new();
+        data.put_u32(1);
+        data.put_str("some text");
+        data.put_unsigned(3u8);
+        for x in [-1, 1000, -100000] { data.put_signed(x); }
+        // 3 fields and 3 array elements
+        assert_eq!(data.len() as u64 + 6 * FIELD_COST, decode_cost(&data, &schema)?);
+        assert!(decode_cost(&data[..10], &schema).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reset_seek() -> Result<()> {
+        let data = [1u8, 2, 3];
+        let mut src = SliceSource::from(&data);
+        assert_eq!(0x0102, src.get_u16()?);
+        src.reset();
+        assert_eq!(1, src.get_u8()?);
+        src.seek(2)?;
+        assert_eq!(3, src.get_u8()?);
+        src.seek(3)?;
+        assert!(src.get_u8().is_err());
+        assert!(src.seek(4).is_err());
+        assert_eq!(3, src.position());
+        Ok(())
+    }
+
+    #[test]
+    fn test_source_dump() -> Result<()> {
+        let data = b"0123456789ABCDEFGHIJ";
+        let mut src = SliceSource::from(data);
+        src.seek(18)?;
+        assert_eq!("0000 49 4a                                           |IJ              |\n",
+            src.dump_remaining());
+        assert!(src.dump_all().starts_with("position 0012 of 0014\n0000 30 31 "));
+        Ok(())
+    }
+
+    #[test]
+    fn test_enum_variants() -> Result<()> {
+        #[derive(Debug, PartialEq)]
+        enum Command {
+            Stop,
+            Move { x: i64, y: i64 },
+        }
+        impl BiPackable for Command {
+            fn bi_pack(self: &Self, sink: &mut impl BipackSink) {
+                match self {
+                    Command::Stop => sink.put_variant(0),
+                    Command::Move { x, y } => {
+                        sink.put_variant(1);
+                        sink.put_signed(*x);
+                        sink.put_signed(*y);
+                    }
+                }
+            }
+        }
+        impl BiUnpackable for Command {
+            fn bi_unpack(source: &mut impl BipackSource) -> Result<Command> {
+                match source.get_variant()? {
+                    0 => Ok(Command::Stop),
+                    1 => Ok(Command::Move { x: source.get_signed()?, y: source.get_signed()? }),
+                    _ => panic!("unknown variant"),
+                }
+            }
+        }
+        let data = bipack!(Command::Move { x: -3, y: 100 }, Command::Stop);
+        assert_eq!("041c210300", hex::encode(&data));
+        let mut src = SliceSource::from(&data);
+        assert_eq!(Command::Move { x: -3, y: 100 }, Command::bi_unpack(&mut src)?);
+        assert_eq!(Command::Stop, Command::bi_unpack(&mut src)?);
+        Ok(())
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn test_testing_helpers() {
+        use crate::testing::*;
+        for x in [0u64, 63, 64, 16384, 4194304, u64::MAX] {
+            assert!(roundtrip_unsigned(x));
+            assert!(sink_matches_reference(VecSink::new, VecSink::into_inner, x));
+            assert!(source_matches_reference(|d| SliceSource::from(d).get_unsigned(), x));
+        }
+        assert!(roundtrip_signed(-1000));
+        assert!(roundtrip_str("тест"));
+    }
+
+    #[test]
+    fn test_max_var_len() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_str("short");
+        data.put_var_bytes(&[0u8; 100]);
+        let mut src = SliceSource::with_max_var_len(&data, 10);
+        assert_eq!("short", src.get_str()?);
+        assert!(matches!(src.get_var_bytes(), Err(BipackError::LimitExceeded { size: 100, limit: 10 })));
+        // huge size is rejected before allocation
+        let mut data = Vec::new();
+        data.put_unsigned(u64::MAX);
+        assert!(matches!(SliceSource::with_max_var_len(&data, 1024).get_str(),
+            Err(BipackError::LimitExceeded { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_little_endian() -> Result<()> {
+        let mut be = Vec::new();
+        be.put_u32(0x01020304);
+        let mut le = Vec::new();
+        le.put_u32_le(0x01020304);
+        assert_eq!("04030201", hex::encode(&le));
+        le.reverse();
+        assert_eq!(be, le);
+
+        let mut data = Vec::new();
+        data.put_u16_le(64000);
+        data.put_u32_le(66000);
+        data.put_u64_le(931127140399);
+        let mut src = SliceSource::from(&data);
+        assert_eq!(64000, src.get_u16_le()?);
+        assert_eq!(66000, src.get_u32_le()?);
+        assert_eq!(931127140399, src.get_u64_le()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_put_seq() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_seq(0..100u32);
+        data.put_seq(["a", "bc"]);
+        let mut src = SliceSource::from(&data);
+        assert_eq!(100, src.get_unsigned()?);
+        for i in 0..100 { assert_eq!(i, src.get_unsigned()?); }
+        assert_eq!(2, src.get_unsigned()?);
+        assert_eq!("a", src.get_str()?);
+        assert_eq!("bc", src.get_str()?);
+
+        // same as Vec, bytes are not smartints
+        let bytes = [0u8, 63, 64, 200, 255];
+        let mut data = Vec::new();
+        data.put_seq(bytes.iter().copied());
+        data.put_seq(bytes.iter());
+        let mut src = SliceSource::from(&data);
+        assert_eq!(bytes.to_vec(), Vec::<u8>::bi_unpack(&mut src)?);
+        assert_eq!(bytes.to_vec(), src.get_var_bytes()?);
+        let mut read = Vec::new();
+        SliceSource::from(&data).for_each_element(|x: u8| {
+            read.push(x);
+            Ok(())
+        })?;
+        assert_eq!(bytes.to_vec(), read);
+        Ok(())
+    }
+
+    #[test]
+    fn test_for_each_element() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_seq(1..11u32);
+        let mut sum = 0;
+        SliceSource::from(&data).for_each_element(|x: u32| {
+            sum += x;
+            Ok(())
+        })?;
+        assert_eq!(55, sum);
+
+        let mut count = 0;
+        let result = SliceSource::from(&data).for_each_element(|x: u32| {
+            count += 1;
+            if x == 3 { Err(BipackError::NonCanonical) } else { Ok(()) }
+        });
+        assert!(matches!(result, Err(BipackError::NonCanonical)));
+        assert_eq!(3, count);
+        assert!(SliceSource::from(&data[..5]).for_each_element(|x: u32| Ok(())).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsigned_boundaries() -> Result<()> {
+        for (value, size) in [(62u64, 1), (63, 1), (64, 2), (16383, 2), (16384, 3)] {
+            let mut data = Vec::new();
+            data.put_unsigned(value);
+            assert_eq!(size, data.len());
+            assert_eq!(value, SliceSource::from(&data).get_unsigned()?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_duration() -> Result<()> {
+        for d in [Duration::new(123, 456789), Duration::ZERO, Duration::MAX] {
+            let mut data = Vec::new();
+            data.put_duration(d);
+            assert_eq!(d, SliceSource::from(&data).get_duration()?);
+        }
+        let mut data = Vec::new();
+        data.put_unsigned(1u8);
+        data.put_unsigned(1_000_000_000u32);
+        assert!(matches!(SliceSource::from(&data).get_duration(), Err(BipackError::InvalidValue)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_system_time() -> Result<()> {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let now = UNIX_EPOCH + Duration::from_secs(seconds);
+        let mut data = Vec::new();
+        data.put_system_time(now)?;
+        assert_eq!(now, SliceSource::from(&data).get_system_time()?);
+
+        let mut data = Vec::new();
+        assert!(matches!(data.put_system_time(UNIX_EPOCH - Duration::from_secs(1)),
+            Err(BipackError::InvalidValue)));
+        assert!(data.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsigned_as() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_unsigned(70000u32);
+        assert!(matches!(SliceSource::from(&data).get_packed_u16(), Err(BipackError::Overflow)));
+        assert!(matches!(u16::bi_unpack(&mut SliceSource::from(&data)), Err(BipackError::Overflow)));
+        assert_eq!(70000, SliceSource::from(&data).get_packed_u32()?);
+        assert_eq!(70000i32, SliceSource::from(&data).get_unsigned_as()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bipack_into() -> Result<()> {
+        let mut data = vec![0xFFu8, 0xFE];
+        bipack_into!(&mut data, 177u32, "hello!");
+        assert_eq!(&[0xFF, 0xFE], &data[..2]);
+        assert_eq!(bipack!(177u32, "hello!"), &data[2..]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pack_u8_and_into_u64() {
+        // scalar u8 is a smartint, only arrays and vectors of it are plain bytes
+        assert_eq!(vec![0x21, 0x03], bipack!(200u8));
+        assert_eq!(vec![200u8, 7], bipack!([200u8, 7]));
+        assert_eq!(vec![2 << 2, 200, 7], bipack!(vec![200u8, 7]));
+
+        #[derive(Clone, Copy)]
+        struct Id(u32);
+        impl crate::bipack_sink::IntoU64 for Id {
+            fn into_u64(self) -> u64 { self.0 as u64 }
+        }
+        bipack_unsigned!(Id);
+        assert_eq!(bipack!(70000u32), bipack!(Id(70000)));
+        assert_eq!(bipack!([1u32, 70000]), bipack!([Id(1), Id(70000)]));
+    }
+
+    #[test]
+    fn test_pack_refs_and_boxes() -> Result<()> {
+        let boxed = Box::new(70000u32);
+        let text = String::from("text");
+        let number = 17u64;
+        let data = bipack!(boxed, text.as_str(), &number, &&boxed);
+        assert_eq!(bipack!(70000u32, "text", 17u64, 70000u32), data);
+        let mut src = SliceSource::from(&data);
+        assert_eq!(boxed, Box::<u32>::bi_unpack(&mut src)?);
+
+        // not through bipack! which derefs its arguments
+        fn pack<T: BiPackable>(value: T) -> Vec<u8> {
+            let mut data = Vec::new();
+            value.bi_pack(&mut data);
+            data
+        }
+        assert_eq!(bipack!(70000u32), pack(Box::new(70000u32)));
+        assert_eq!(bipack!("text"), pack(&text));
+        let strings = vec![text.clone(), "more".to_string()];
+        assert_eq!(pack(&strings), pack(strings.iter().collect::<Vec<&String>>()));
+        assert_eq!(pack(Some(70000u32)), pack(Some(Box::new(70000u32))));
+        let mut seq = Vec::new();
+        seq.put_seq(strings.iter());
+        assert_eq!(pack(&strings), seq);
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_sink() {
+        let mut buffer = [0u8; 6];
+        let mut sink = SliceSink::from(&mut buffer);
+        assert_eq!(Ok(()), sink.try_put_u16(0x1234));
+        assert_eq!(Ok(()), sink.try_put_unsigned(70000u32));
+        assert_eq!(Err(SinkFull), sink.try_put_u32(1));
+        assert_eq!(Err(SinkFull), sink.try_put_str("long"));
+        assert_eq!(5, sink.position());
+        assert_eq!(Ok(()), sink.try_put_signed(-1));
+        assert_eq!(Err(SinkFull), sink.try_put_u8(0));
+
+        let mut expected = Vec::new();
+        expected.put_u16(0x1234);
+        expected.put_unsigned(70000u32);
+        expected.put_signed(-1);
+        assert_eq!(expected, buffer);
+    }
+
+    #[test]
+    fn test_unsigned_type3_boundaries() -> Result<()> {
+        for (value, size) in [(V2LIMIT - 1, 3), (V2LIMIT, 4), (V2LIMIT + 1, 4),
+                              ((1 << 29) - 1, 4), (1 << 29, 5), (1_000_000_000_000, 6),
+                              (u64::MAX, 9)] {
+            let mut data = Vec::new();
+            data.put_unsigned(value);
+            assert_eq!(size, data.len(), "size of {}", value);
+            assert_eq!(value, SliceSource::from(&data).get_unsigned()?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitset() -> Result<()> {
+        let bits = [true, false, true, true, false, false, false, false, true, true];
+        let mut data = Vec::new();
+        data.put_bits(&bits);
+        data.put_bits(&[]);
+        assert_eq!("28b0c000", hex::encode(&data));
+        let mut src = SliceSource::from(&data);
+        assert_eq!(bits.to_vec(), src.get_bits()?);
+        assert!(src.get_bits()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_flags() -> Result<()> {
+        for flags in [0, 1, 0xFFFF_FFFF_FFFF_FFFF] {
+            let mut data = Vec::new();
+            data.put_flags(flags);
+            assert_eq!(flags, SliceSource::from(&data).get_flags()?);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_bytes_source() -> Result<()> {
+        use crate::bytes_source::BytesSource;
+        let mut data = Vec::new();
+        data.put_u16(0x1234);
+        data.put_var_bytes(b"shared payload");
+        data.put_str("tail");
+        let buffer = bytes::Bytes::from(data);
+        let mut src = BytesSource::from(buffer.clone());
+        assert_eq!(0x1234, src.get_u16()?);
+        let payload = src.get_var_bytes_shared()?;
+        assert_eq!(&b"shared payload"[..], &payload[..]);
+        assert_eq!(buffer[3..].as_ptr(), payload.as_ptr());
+        assert_eq!("tail", src.get_str()?);
+        assert!(src.get_var_bytes_shared().is_err());
+
+        // the size is checked before allocating
+        let mut hostile = Vec::new();
+        hostile.put_unsigned(i64::MAX as u64);
+        hostile.put_str("short");
+        let mut src = BytesSource::from(bytes::Bytes::from(hostile));
+        assert!(matches!(src.get_str(), Err(BipackError::NoDataError { offset: 9, .. })));
+        Ok(())
+    }
+
+    /// Run future that never waits, enough to test async code over in-memory data.
+    #[cfg(feature = "tokio")]
+    fn block_on<F: std::future::Future>(f: F) -> F::Output {
+        let mut f = std::pin::pin!(f);
+        let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+        loop {
+            if let std::task::Poll::Ready(x) = f.as_mut().poll(&mut cx) { return x; }
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_async_source() -> Result<()> {
+        use crate::async_source::AsyncReadSource;
+        let mut data = Vec::new();
+        data.put_u8(7);
+        data.put_u32(66000);
+        data.put_unsigned(931127140399u64);
+        data.put_signed(-100);
+        data.put_str("async");
+        block_on(async {
+            let mut src = AsyncReadSource::new(data.as_slice());
+            assert_eq!(7, src.get_u8().await?);
+            assert_eq!(66000, src.get_u32().await?);
+            assert_eq!(931127140399, src.get_unsigned().await?);
+            assert_eq!(-100, src.get_signed().await?);
+            assert_eq!("async", src.get_str().await?);
+            assert!(matches!(src.get_u8().await,
+                Err(BipackError::NoDataError { offset, needed: 1 }) if offset == data.len()));
+            Ok(())
+        })
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_async_varint_overflow() -> Result<()> {
+        use crate::async_source::AsyncReadSource;
+        let mut max = Vec::new();
+        max.put_var_unsigned(u64::MAX);
+        let too_long = [0xFFu8; 12];
+        block_on(async {
+            assert_eq!(u64::MAX, AsyncReadSource::new(max.as_slice()).get_varint_unsigned().await?);
+            let mut src = AsyncReadSource::new(&too_long[..]);
+            assert!(matches!(src.get_varint_unsigned().await, Err(BipackError::Overflow)));
+            Ok(())
+        })
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_async_sink() -> std::io::Result<()> {
+        use crate::async_sink::AsyncWriteSink;
+        let mut expected = Vec::new();
+        expected.put_u8(7);
+        expected.put_u32(66000);
+        expected.put_unsigned(931127140399u64);
+        expected.put_signed(-100);
+        expected.put_str("async");
+        block_on(async {
+            let mut sink = AsyncWriteSink::with_buffer_size(Vec::new(), 4);
+            sink.put_u8(7).await?;
+            sink.put_u32(66000).await?;
+            sink.put_unsigned(931127140399u64).await?;
+            sink.put_signed(-100).await?;
+            sink.put("async").await?;
+            sink.flush().await?;
+            assert_eq!(expected, sink.into_inner());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_nested() -> Result<()> {
+        #[derive(Debug, PartialEq)]
+        struct Point {
+            x: u32,
+            y: u32,
+        }
+        impl BiPackable for Point {
+            fn bi_pack(self: &Self, sink: &mut impl BipackSink) {
+                sink.put_unsigned(self.x);
+                sink.put_unsigned(self.y);
+            }
+        }
+        impl BiUnpackable for Point {
+            fn bi_unpack(source: &mut impl BipackSource) -> Result<Point> {
+                Ok(Point { x: source.get_packed_u32()?, y: source.get_packed_u32()? })
+            }
+        }
+        let mut data = Vec::new();
+        data.put_nested(&Point { x: 1, y: 1000 });
+        data.put_u8(0xAA);
+        // the blob is shorter than the value needs
+        data.put_nested(&17u32);
+        data.put_u8(1);
+        let mut src = SliceSource::from(&data);
+        assert_eq!(Point { x: 1, y: 1000 }, src.get_nested()?);
+        assert_eq!(0xAA, src.get_u8()?);
+        assert!(src.get_nested::<Point>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_smartint_doc_example() -> Result<()> {
+        assert_eq!("f67f01", encode_unsigned_hex(24573));
+        assert_eq!(24573, SliceSource::from(&[0xF6, 0x7F, 0x01]).get_unsigned()?);
+        assert_eq!("fc", encode_unsigned_hex(63));
+        assert_eq!("0101", encode_unsigned_hex(64));
+        Ok(())
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64_tools() {
+        use crate::tools::{from_base64, to_base64};
+        let mut data = Vec::new();
+        data.put_str("Hello");
+        data.put_unsigned(24573u32);
+        let text = to_base64(&data);
+        assert_eq!(data, from_base64(&text).unwrap());
+        assert_eq!(vec![7u8, 250, 0, 0, 1, 1, 208, 0, 0, 0, 216, 203, 128, 160, 47],
+                   from_base64("B/oAAAEB0AAAANjLgKAv").unwrap());
+        assert!(from_base64("not base64!").is_err());
+    }
+
+    #[test]
+    fn test_get_str_max() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_unsigned(1_000_000u32);
+        data.put_fixed_bytes(b"not that long");
+        let mut src = SliceSource::from(&data);
+        match src.get_str_max(64) {
+            Err(BipackError::LimitExceeded { size, limit }) => {
+                assert_eq!(1_000_000, size);
+                assert_eq!(64, limit);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(data.len() - 13, src.position());
+
+        let mut data = Vec::new();
+        data.put_str("fits");
+        data.put_str("exactly");
+        let mut src = SliceSource::from(&data);
+        assert_eq!("fits", src.get_str_max(64)?);
+        assert_eq!("exactly", src.get_str_max(7)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_slice_source_bytes() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_u16(0x0102);
+        data.put_str("abc");
+        data.put_u8(7);
+        let mut src = SliceSource::from(&data);
+        assert_eq!(0x0102, src.get_u16()?);
+        assert_eq!(vec![3u8 << 2, b'a'], src.bytes().take(2).collect::<Vec<_>>());
+        assert_eq!(b'b', src.get_u8()?);
+        assert_eq!(2, src.bytes().count());
+        assert_eq!(0, src.bytes().count());
+        assert!(src.get_u8().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_opt_str() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_opt_str(None);
+        data.put_opt_str(Some(""));
+        data.put_opt_str(Some("x"));
+        assert_eq!(vec![0u8, 1 << 2, 2 << 2, b'x'], data);
+        let mut src = SliceSource::from(&data);
+        assert_eq!(None, src.get_opt_str()?);
+        assert_eq!(Some(String::new()), src.get_opt_str()?);
+        assert_eq!(Some("x".to_string()), src.get_opt_str()?);
+        assert!(src.get_opt_str().is_err());
+
+        let mut hostile = Vec::new();
+        hostile.put_unsigned(u64::MAX);
+        let mut src = SliceSource::with_max_var_len(&data[1..], 0);
+        assert_eq!(Some(String::new()), src.get_opt_str()?);
+        assert!(matches!(src.get_opt_str(), Err(BipackError::LimitExceeded { size: 1, limit: 0 })));
+        let result = SliceSource::with_max_var_len(&hostile, 100).get_opt_str();
+        assert!(matches!(result, Err(BipackError::LimitExceeded { limit: 100, .. })));
+        let result = TracingSource::new(SliceSource::strict(&hostile)).get_opt_str();
+        assert!(matches!(result, Err(BipackError::NoDataError { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_packed_u8() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_unsigned(255u32);
+        data.put_unsigned(300u32);
+        let mut src = SliceSource::from(&data);
+        assert_eq!(255, src.get_packed_u8()?);
+        assert!(matches!(src.get_packed_u8(), Err(BipackError::Overflow)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_varint_signed() -> Result<()> {
+        // reference values of protobuf sint64 encoding
+        for (value, expected) in [(0i64, "00"), (-1, "01"), (1, "02"), (-64, "7f"), (64, "8001"),
+                                  (150, "ac02"), (i64::MAX, "feffffffffffffffff01"),
+                                  (i64::MIN, "ffffffffffffffffff01")] {
+            let mut data = Vec::new();
+            data.put_varint_signed(value);
+            assert_eq!(expected, hex::encode(&data));
+            assert_eq!(value, SliceSource::from(&data).get_varint_signed()?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_size_sink() {
+        let value = ("Hello, size", 24573u32, [1u64, 2, 3, u64::MAX]);
+        let mut size = SizeSink::new();
+        assert!(size.is_empty());
+        value.0.bi_pack(&mut size);
+        value.1.bi_pack(&mut size);
+        value.2.bi_pack(&mut size);
+        size.put_signed(-100);
+
+        let mut data = Vec::with_capacity(size.len());
+        value.0.bi_pack(&mut data);
+        value.1.bi_pack(&mut data);
+        value.2.bi_pack(&mut data);
+        data.put_signed(-100);
+        assert_eq!(data.len(), size.len());
+    }
+
+    #[test]
+    fn test_get_unsigned_counted() -> Result<()> {
+        let values = [0u64, 63, 64, 16383, 16384, 4194303, 4194304, u32::MAX as u64, u64::MAX];
+        let mut data = Vec::new();
+        for v in values { data.put_unsigned(v); }
+        let sizes = [1usize, 1, 2, 2, 3, 3, 4, 5, 9];
+        let mut src = SliceSource::from(&data);
+        for (v, size) in values.iter().zip(sizes) {
+            assert_eq!((*v, size), src.get_unsigned_counted()?);
+        }
+        // default trait implementation counts the same
+        struct ByteSource<'a>(&'a [u8]);
+        impl BipackSource for ByteSource<'_> {
+            fn get_u8(&mut self) -> Result<u8> {
+                let (first, rest) = self.0.split_first()
+                    .ok_or(BipackError::NoDataError { offset: 0, needed: 1 })?;
+                self.0 = rest;
+                Ok(*first)
+            }
+        }
+        let mut src = ByteSource(&data);
+        for (v, size) in values.iter().zip(sizes) {
+            assert_eq!((*v, size), src.get_unsigned_counted()?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_exact() -> Result<()> {
+        let mut hash = [0u8; 32];
+        for (i, b) in hash.iter_mut().enumerate() { *b = i as u8 * 7; }
+        let mut data = Vec::new();
+        data.put_exact(&hash);
+        data.put_exact(&[1, 2, 3]);
+        assert_eq!(35, data.len());
+        let mut src = SliceSource::from(&data);
+        assert_eq!(hash, src.get_exact::<32>()?);
+        assert!(matches!(src.get_exact::<4>(), Err(BipackError::NoDataError { offset: 32, needed: 1 })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_nonzero() -> Result<()> {
+        use std::num::{NonZeroU32, NonZeroU64, NonZeroU8};
+        let id = NonZeroU32::new(24573).unwrap();
+        let data = bipack!(id, NonZeroU64::new(u64::MAX).unwrap(), NonZeroU8::new(200).unwrap());
+        assert_eq!(bipack!(24573u32, u64::MAX, 200u32), data);
+        let mut src = SliceSource::from(&data);
+        assert_eq!(id, NonZeroU32::bi_unpack(&mut src)?);
+        assert_eq!(u64::MAX, NonZeroU64::bi_unpack(&mut src)?.get());
+        assert_eq!(200, NonZeroU8::bi_unpack(&mut src)?.get());
+
+        let zero = bipack!(0u32);
+        assert!(matches!(NonZeroU32::bi_unpack(&mut SliceSource::from(&zero)), Err(BipackError::InvalidValue)));
+        let big = bipack!(300u32);
+        assert!(matches!(NonZeroU8::bi_unpack(&mut SliceSource::from(&big)), Err(BipackError::Overflow)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_str_into() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_str("a longer string to allocate the buffer");
+        data.put_str("short");
+        data.put_var_bytes(&[0xC3, 0x28]);
+        let mut src = SliceSource::from(&data);
+        let mut text = String::new();
+        src.get_str_into(&mut text)?;
+        assert_eq!("a longer string to allocate the buffer", text);
+        let capacity = text.capacity();
+        src.get_str_into(&mut text)?;
+        assert_eq!("short", text);
+        assert_eq!(capacity, text.capacity());
+        assert!(matches!(src.get_str_into(&mut text), Err(BipackError::BadEncoding(_))));
+        assert!(text.is_empty());
+        assert_eq!(capacity, text.capacity());
+        assert!(src.get_str_into(&mut text).is_err());
+
+        let mut src = SliceSource::with_max_var_len(&data, 10);
+        text.push_str("garbage");
+        assert!(matches!(src.get_str_into(&mut text), Err(BipackError::LimitExceeded { .. })));
+        assert!(text.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_smartint_limits() {
+        assert_eq!((64, 16384, 4194304), (V0LIMIT, V1LIMIT, V2LIMIT));
+        for (value, size) in [(V0LIMIT - 1, 1), (V0LIMIT, 2), (V1LIMIT - 1, 2), (V1LIMIT, 3),
+                              (V2LIMIT - 1, 3), (V2LIMIT, 4)] {
+            let mut data = Vec::new();
+            data.put_unsigned(value);
+            assert_eq!(size, data.len(), "size of {}", value);
+        }
+    }
+
+    #[test]
+    fn test_finish() -> Result<()> {
+        let data = bipack!("hello", 24573u32);
+        let mut src = SliceSource::from(&data);
+        src.get_str()?;
+        assert!(matches!(src.finish(), Err(BipackError::TrailingData { remaining: 3 })));
+        let mut src = SliceSource::from(&data);
+        src.get_str()?;
+        src.get_unsigned()?;
+        src.finish()?;
+
+        assert_eq!([7u8, 8], from_slice_exact::<[u8; 2]>(&[7, 8])?);
+        assert!(matches!(from_slice_exact::<u32>(&data), Err(BipackError::TrailingData { remaining: 8 })));
+        assert_eq!("1 unread byte(s) after the data",
+                   from_slice_exact::<u8>(&[1 << 2, 2]).unwrap_err().to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_enum_codec() -> Result<()> {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        #[repr(u8)]
+        enum Color { Red = 1, Green = 2, Blue = 200 }
+
+        impl EnumCodec for Color {
+            fn to_tag(&self) -> u64 { *self as u64 }
+
+            fn from_tag(tag: u64) -> Result<Color> {
+                match tag {
+                    1 => Ok(Color::Red),
+                    2 => Ok(Color::Green),
+                    200 => Ok(Color::Blue),
+                    _ => Err(BipackError::UnknownVariant(tag)),
+                }
+            }
+        }
+        bipack_enum!(Color);
+
+        let data = bipack!(Color::Red, Color::Blue, [Color::Green, Color::Red]);
+        assert_eq!(bipack!([1u8 << 2], 200u32, [2u8 << 2], [1u8 << 2]), data);
+        let mut src = SliceSource::from(&data);
+        assert_eq!(Color::Red, Color::bi_unpack(&mut src)?);
+        assert_eq!(Color::Blue, Color::bi_unpack(&mut src)?);
+        assert_eq!([Color::Green, Color::Red], <[Color; 2]>::bi_unpack(&mut src)?);
+        let bad = bipack!(3u32);
+        assert!(matches!(Color::bi_unpack(&mut SliceSource::from(&bad)), Err(BipackError::UnknownVariant(3))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_describe() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_u32(1234);
+        data.put_str("hello");
+        data.put_unsigned(2u32);
+        data.put_unsigned(1u32);
+        data.put_unsigned(2u32);
+        data.put_signed(-100);
+        data.put_var_bytes(&[0xCA, 0xFE]);
+        let schema = Schema(vec![Field::FixedU32, Field::Str, Field::Array(Box::new(Field::SmartUint)),
+                                 Field::SmartInt, Field::VarBytes]);
+        assert_eq!("0000 #0 FixedU32 = 1234\n\
+                    0004 #1 Str = \"hello\"\n\
+                    000A #2 Array[2]\n\
+                    000B   #2.0 SmartUint = 1\n\
+                    000C   #2.1 SmartUint = 2\n\
+                    000D #3 SmartInt = -100\n\
+                    000F #4 VarBytes[2] = cafe\n", describe(&data, &schema)?);
+
+        data.put_u8(0);
+        assert!(describe(&data, &schema)?.ends_with("0012 1 trailing byte(s)\n"));
+        let schema = Schema(vec![Field::FixedU32, Field::FixedU64]);
+        assert!(matches!(describe(&data[..10], &schema), Err(BipackError::NoDataError { offset: 4, needed: 2 })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_sorted() -> Result<()> {
+        let mut first = BTreeMap::new();
+        let mut second = BTreeMap::new();
+        for (k, v) in [("one", 1u32), ("two", 2), ("three", 3), ("four", 4)] {
+            first.insert(k, v);
+        }
+        for (k, v) in [("four", 4u32), ("three", 3), ("one", 1), ("two", 2)] {
+            second.insert(k, v);
+        }
+        let mut a = Vec::new();
+        a.put_map_sorted(&first);
+        let mut b = Vec::new();
+        b.put_map_sorted(&second);
+        assert_eq!(crc32(&a), crc32(&b));
+        assert_eq!(a, b);
+        let decoded = SliceSource::from(&a).get_map_sorted::<String, u32>()?;
+        assert!(decoded.iter().map(|(k, v)| (k.as_str(), *v)).eq(first.into_iter()));
+
+        let mut bytes = BTreeMap::new();
+        for (k, v) in [(200u8, 64u8), (5, 255), (64, 0)] {
+            bytes.insert(k, v);
+        }
+        let mut data = Vec::new();
+        data.put_map_sorted(&bytes);
+        assert_eq!(bytes, SliceSource::from(&data).get_map_sorted::<u8, u8>()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_strict_source() -> Result<()> {
+        // 5 in 2 bytes instead of 1
+        let long_smartint = [(5 << 2) | 1, 0];
+        assert_eq!(5, SliceSource::from(&long_smartint).get_unsigned()?);
+        assert!(matches!(SliceSource::strict(&long_smartint).get_unsigned(), Err(BipackError::NonCanonical)));
+
+        // "hi" with the size in 2 bytes
+        let long_size = [(2 << 2) | 1, 0, b'h', b'i'];
+        assert_eq!("hi", SliceSource::from(&long_size).get_str()?);
+        assert!(matches!(SliceSource::strict(&long_size).get_str(), Err(BipackError::NonCanonical)));
+
+        // varint with bits beyond u64 and with trailing zero byte
+        let too_long = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F];
+        assert_eq!(u64::MAX, SliceSource::from(&too_long).get_varint_unsigned()?);
+        assert!(matches!(SliceSource::strict(&too_long).get_varint_unsigned(), Err(BipackError::Overflow)));
+        let longest = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01];
+        assert_eq!(u64::MAX, SliceSource::from(&longest).get_varint_unsigned()?);
+        assert!(matches!(SliceSource::strict(&longest).get_varint_unsigned(), Err(BipackError::Overflow)));
+        let zero_tail = [0x81, 0x00];
+        assert_eq!(1, SliceSource::from(&zero_tail).get_varint_unsigned()?);
+        let mut src = SliceSource::from(&zero_tail);
+        src.set_strict(true);
+        assert!(matches!(src.get_varint_unsigned(), Err(BipackError::NonCanonical)));
+
+        // canonical data are read the same way in both modes
+        let mut data = Vec::new();
+        data.put_unsigned(u64::MAX);
+        data.put_var_unsigned(u64::MAX);
+        data.put_str("hello");
+        let mut src = SliceSource::strict(&data);
+        assert_eq!(u64::MAX, src.get_unsigned()?);
+        assert_eq!(u64::MAX, src.get_varint_unsigned()?);
+        assert_eq!("hello", src.get_str()?);
+
+        // size bigger than the data is rejected before allocating
+        let mut huge = Vec::new();
+        huge.put_unsigned(u32::MAX);
+        huge.put_str("x");
+        assert!(matches!(SliceSource::strict(&huge).get_var_bytes(),
+            Err(BipackError::NoDataError { offset: 5, needed }) if needed == u32::MAX as usize - 2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_into_u64_refs_and_nonzero() {
+        struct Record { ids: Vec<u32>, size: std::num::NonZeroU64 }
+        let r = Record { ids: vec![24573, 7], size: std::num::NonZeroU64::new(70000).unwrap() };
+        let mut data = Vec::new();
+        for id in &r.ids { data.put_unsigned(id); }
+        data.put_unsigned(r.size);
+        assert_eq!(bipack!(24573u32, 7u32, 70000u32), data);
+    }
+
+    #[test]
+    fn test_put_unsigned_checked() {
+        let mut data = Vec::new();
+        assert_eq!(Ok(()), data.put_unsigned_checked(24573i64));
+        assert_eq!(Ok(()), data.put_unsigned_checked(0i32));
+        assert_eq!(Err(NegativeValue), data.put_unsigned_checked(-1i32));
+        assert_eq!(Err(NegativeValue), data.put_unsigned_checked(i64::MIN));
+        assert_eq!(bipack!(24573u32, 0u32), data);
+    }
+
+    #[test]
+    fn test_slice_source_fixed_ints() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_u64(0x0102030405060708);
+        data.put_u32(0xA1B2C3D4);
+        data.put_u16(0xBEEF);
+        let mut src = SliceSource::from(&data);
+        assert_eq!(0x0102030405060708, src.get_u64()?);
+        assert_eq!(0xA1B2C3D4, src.get_u32()?);
+        assert!(matches!(src.get_u32(), Err(BipackError::NoDataError { offset: 12, needed: 2 })));
+        // failed read does not consume data
+        assert_eq!(0xBEEF, src.get_u16()?);
+        assert!(matches!(src.get_u64(), Err(BipackError::NoDataError { offset: 14, needed: 8 })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_slice_source_fixed_bytes() -> Result<()> {
+        // the source using default trait methods
+        struct ByteSource<'a>(&'a [u8]);
+        impl BipackSource for ByteSource<'_> {
+            fn get_u8(&mut self) -> Result<u8> {
+                let (first, rest) = self.0.split_first()
+                    .ok_or(BipackError::NoDataError { offset: 0, needed: 1 })?;
+                self.0 = rest;
+                Ok(*first)
+            }
+        }
+        let data: Vec<u8> = (0..2000).map(|i| (i * 7) as u8).collect();
+        for size in [0, 1, 1024, 2000] {
+            let mut src = SliceSource::from(&data);
+            assert_eq!(ByteSource(&data).get_fixed_bytes(size)?, src.get_fixed_bytes(size)?);
+            assert_eq!(size, src.position());
+        }
+        let mut src = SliceSource::from(&data);
+        src.get_fixed_bytes(1000)?;
+        assert!(ByteSource(&data[1000..]).get_fixed_bytes(1024).is_err());
+        assert!(matches!(src.get_fixed_bytes(1024), Err(BipackError::NoDataError { offset: 1000, needed: 24 })));
+        assert_eq!(1000, src.position());
+        Ok(())
+    }
+
+    #[test]
+    fn test_vec_sink_bulk_output() {
+        // the sink using default trait methods
+        struct ByteSink(Vec<u8>);
+        impl BipackSink for ByteSink {
+            fn put_u8(&mut self, data: u8) { self.0.push(data) }
+        }
+        let blob: Vec<u8> = (0..5000).map(|i| (i * 13) as u8).collect();
+        let mut expected = ByteSink(Vec::new());
+        let mut data = Vec::new();
+        expected.put_fixed_bytes(&blob);
+        data.put_fixed_bytes(&blob);
+        expected.put_var_bytes(&blob[..100]);
+        data.put_var_bytes(&blob[..100]);
+        expected.put_str("bulk");
+        data.put_str("bulk");
+        assert_eq!(expected.0, data);
+    }
+
+    #[test]
+    fn test_ip_addr() -> Result<()> {
+        use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+        let v4: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        let v6 = SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 443);
+        let mut data = Vec::new();
+        data.put_socket_addr(v4);
+        data.put_socket_addr(v6);
+        data.put_ip_addr(v6.ip());
+        assert_eq!("047f000001417e", hex::encode(&data[..7]));
+        let mut src = SliceSource::from(&data);
+        assert_eq!(v4, src.get_socket_addr()?);
+        assert_eq!(v6, src.get_socket_addr()?);
+        assert_eq!(v6.ip(), src.get_ip_addr()?);
+        src.finish()?;
+
+        assert!(matches!(SliceSource::from(&[5, 1, 2, 3, 4]).get_ip_addr(), Err(BipackError::InvalidValue)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_str_unchecked() -> Result<()> {
+        let data = bipack!("trusted ✓", "");
+        let mut src = SliceSource::from(&data);
+        // safe: packed from valid strings
+        unsafe {
+            assert_eq!("trusted ✓", src.get_str_unchecked()?);
+            assert_eq!("", src.get_str_unchecked()?);
+            assert!(src.get_str_unchecked().is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_str_cow() -> Result<()> {
+        use std::borrow::Cow;
+        let data = bipack!("borrowed", "ok");
+        let first = {
+            let mut src = SliceSource::from(&data);
+            let first = src.get_str_cow()?;
+            assert_eq!("ok", src.get_str_cow()?);
+            first
+        };
+        match first {
+            Cow::Borrowed(s) => {
+                assert_eq!("borrowed", s);
+                assert_eq!(data[1..].as_ptr(), s.as_ptr());
+            }
+            Cow::Owned(_) => panic!("string is copied"),
+        }
+        let mut bad = Vec::new();
+        bad.put_var_bytes(&[0xC3, 0x28]);
+        let mut src = SliceSource::from(&bad);
+        assert!(matches!(src.get_str_cow(), Err(BipackError::BadEncoding(_))));
+        assert_eq!(1, src.position());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fixed_i8() -> Result<()> {
+        let samples = [-128i8, -1, 0, 1, 127];
+        let mut data = Vec::new();
+        data.put_fixed_i8(&samples);
+        assert_eq!(vec![0x80u8, 0xFF, 0, 1, 0x7F], data);
+        let mut src = SliceSource::from(&data);
+        assert_eq!(samples.to_vec(), src.get_fixed_i8(5)?);
+        assert!(src.get_fixed_i8(1).is_err());
+
+        // written in bulk
+        struct SliceCounter(Vec<usize>);
+        impl BipackSink for SliceCounter {
+            fn put_u8(self: &mut Self, data: u8) { self.0.push(1) }
+            fn put_slice(self: &mut Self, data: &[u8]) { self.0.push(data.len()) }
+        }
+        let mut counter = SliceCounter(Vec::new());
+        counter.put_fixed_i8(&samples);
+        assert_eq!(vec![5], counter.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_u24() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_u24(0);
+        data.put_u24(0xFFFFFF);
+        data.put_u24(0x123456);
+        assert_eq!("000000ffffff123456", hex::encode(&data));
+        let mut src = SliceSource::from(&data);
+        assert_eq!(0, src.get_u24()?);
+        assert_eq!(0xFFFFFF, src.get_u24()?);
+        assert_eq!(0x123456, src.get_u24()?);
+        assert!(src.get_u24().is_err());
+        assert!(std::panic::catch_unwind(|| Vec::new().put_u24(1 << 24)).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "smallvec")]
+    #[test]
+    fn test_get_smallvec() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_seq([1u32, 2, 300]);
+        data.put_seq([1u32, 2, 3, 4, 5]);
+        let mut src = SliceSource::from(&data);
+        let short = src.get_smallvec::<u32, 4>()?;
+        assert!(!short.spilled());
+        assert_eq!(&[1, 2, 300], short.as_slice());
+        let long = src.get_smallvec::<u32, 4>()?;
+        assert!(long.spilled());
+        assert_eq!(&[1, 2, 3, 4, 5], long.as_slice());
+        let mut bytes = Vec::new();
+        bytes.put_var_bytes(&[64, 200, 255]);
+        assert_eq!(&[64u8, 200, 255], SliceSource::from(&bytes).get_smallvec::<u8, 4>()?.as_slice());
+
+        let mut huge = Vec::new();
+        huge.put_unsigned(u64::MAX);
+        assert!(matches!(SliceSource::from(&huge).get_smallvec::<u32, 4>(), Err(BipackError::NoDataError { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hex_tools() -> Result<()> {
+        let mut data = vec![7u8];
+        data.put_u16(64000);
+        data.put_u32(66000);
+        data.put_u64(931127140399);
+        assert_eq!("07fa00000101d0000000d8cb80a02f", to_hex(&data));
+        assert_eq!(hex::encode(&data), to_hex(&data));
+        assert_eq!(data, from_hex("07fa00000101d0000000d8cb80a02f")?);
+        assert_eq!(vec![0xF6u8, 0x7F, 0x01], from_hex("F67f01")?);
+        assert_eq!(Vec::<u8>::new(), from_hex("")?);
+        assert!(matches!(from_hex("f67"), Err(BipackError::InvalidValue)));
+        assert!(matches!(from_hex("0g"), Err(BipackError::InvalidValue)));
+        Ok(())
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_fixed_roundtrip(a: u8, b: u16, c: u32, d: u64, e: i8, f: i16, g: i32, h: i64) {
+            let mut data = Vec::new();
+            data.put_u8(a);
+            data.put_u16(b);
+            data.put_u32(c);
+            data.put_u64(d);
+            data.put_i8(e);
+            data.put_i16(f);
+            data.put_i32(g);
+            data.put_i64(h);
+            data.put_u16_le(b);
+            data.put_u32_le(c);
+            data.put_u64_le(d);
+            let mut src = SliceSource::from(&data);
+            proptest::prop_assert_eq!(a, src.get_u8()?);
+            proptest::prop_assert_eq!(b, src.get_u16()?);
+            proptest::prop_assert_eq!(c, src.get_u32()?);
+            proptest::prop_assert_eq!(d, src.get_u64()?);
+            proptest::prop_assert_eq!(e, src.get_i8()?);
+            proptest::prop_assert_eq!(f, src.get_i16()?);
+            proptest::prop_assert_eq!(g, src.get_i32()?);
+            proptest::prop_assert_eq!(h, src.get_i64()?);
+            proptest::prop_assert_eq!(b, src.get_u16_le()?);
+            proptest::prop_assert_eq!(c, src.get_u32_le()?);
+            proptest::prop_assert_eq!(d, src.get_u64_le()?);
+            proptest::prop_assert!(src.finish().is_ok());
+        }
+
+        #[test]
+        fn prop_smartint_roundtrip(a: u8, b: u16, c: u32, d: u64, e in (i64::MIN + 1)..=i64::MAX) {
+            let mut data = Vec::new();
+            data.put_unsigned(a);
+            data.put_unsigned(b);
+            data.put_unsigned(c);
+            data.put_unsigned(d);
+            data.put_signed(e);
+            data.put_varint_signed(e);
+            let mut src = SliceSource::strict(&data);
+            proptest::prop_assert_eq!(a, src.get_packed_u8()?);
+            proptest::prop_assert_eq!(b, src.get_packed_u16()?);
+            proptest::prop_assert_eq!(c, src.get_packed_u32()?);
+            proptest::prop_assert_eq!(d, src.get_unsigned()?);
+            proptest::prop_assert_eq!(e, src.get_signed()?);
+            proptest::prop_assert_eq!(e, src.get_varint_signed()?);
+            proptest::prop_assert!(src.finish().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_tracing_source() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_str("magic");
+        data.put_u8(1);
+        let mut src = TracingSource::new(SliceSource::from(&data));
+        let result = src.named("header", |s| {
+            let magic = s.named("magic", |s| s.get_str())?;
+            let major = s.named("major", |s| s.get_u8())?;
+            let minor = s.named("minor", |s| s.get_u8())?;
+            Ok((magic, major, minor))
+        });
+        match result {
+            Err(BipackError::AtPath { path, error }) => {
+                assert_eq!(vec!["header", "minor"], path);
+                assert!(matches!(*error, BipackError::NoDataError { offset: 7, needed: 1 }));
+            }
+            x => panic!("unexpected result: {:?}", x),
+        }
+        let e = TracingSource::new(SliceSource::from(&data[..3]))
+            .named("header", |s| s.named("magic", |s| s.get_str())).unwrap_err();
+        assert_eq!("header.magic: not enough data at offset 1: 3 more byte(s) needed", e.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_var_bytes_into() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_var_bytes(&[1u8; 100]);
+        data.put_var_bytes(&[2, 3]);
+        data.put_unsigned(1000u32);
+        let mut src = SliceSource::from(&data);
+        let mut buffer = Vec::new();
+        assert_eq!(100, src.get_var_bytes_into(&mut buffer)?);
+        assert_eq!(vec![1u8; 100], buffer);
+        let capacity = buffer.capacity();
+        assert_eq!(2, src.get_var_bytes_into(&mut buffer)?);
+        assert_eq!(vec![2u8, 3], buffer);
+        assert_eq!(capacity, buffer.capacity());
+        assert!(matches!(src.get_var_bytes_into(&mut buffer), Err(BipackError::NoDataError { offset: 107, needed: 1000 })));
+        assert!(buffer.is_empty());
+        assert_eq!(capacity, buffer.capacity());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_into_hostile_size() -> Result<()> {
+        struct ByteSource<'a>(&'a [u8], usize);
+        impl BipackSource for ByteSource<'_> {
+            fn get_u8(self: &mut Self) -> Result<u8> {
+                let b = *self.0.get(self.1).ok_or(BipackError::NoDataError { offset: self.1, needed: 1 })?;
+                self.1 += 1;
+                Ok(b)
+            }
+        }
+        let mut data = Vec::new();
+        data.put_unsigned(i64::MAX as u64);
+        data.put_fixed_bytes(&[1; 5000]);
+        let mut buffer = vec![9u8; 3];
+        let result = ByteSource(&data, 0).get_var_bytes_into(&mut buffer);
+        assert!(matches!(result, Err(BipackError::NoDataError { .. })));
+        assert!(buffer.is_empty());
+        let result = TracingSource::new(SliceSource::from(&data)).get_var_bytes_into(&mut buffer);
+        assert!(matches!(result, Err(BipackError::NoDataError { .. })));
+        let result = DictSource::new(ByteSource(&data, 0)).get_var_bytes_into(&mut buffer);
+        assert!(matches!(result, Err(BipackError::NoDataError { .. })));
+        assert!(buffer.is_empty());
+
+        let mut text = "kept".to_string();
+        let result = ByteSource(&data, 0).get_str_into(&mut text);
+        assert!(matches!(result, Err(BipackError::NoDataError { .. })));
+        assert!(text.is_empty());
+        let result = TracingSource::new(ByteSource(&data, 0)).get_str_into(&mut text);
+        assert!(matches!(result, Err(BipackError::NoDataError { .. })));
+
+        // the size itself is truncated
+        for partial in [&data[..1], &data[..0]] {
+            let mut text = "kept".to_string();
+            assert!(ByteSource(partial, 0).get_str_into(&mut text).is_err());
+            assert!(text.is_empty());
+            let mut text = "kept".to_string();
+            assert!(TracingSource::new(ByteSource(partial, 0)).get_str_into(&mut text).is_err());
+            assert!(text.is_empty());
+            let mut text = "kept".to_string();
+            assert!(DictSource::new(ByteSource(partial, 0)).get_str_into(&mut text).is_err());
+            assert!(text.is_empty());
+        }
+
+        // sizes over the chunk are read completely
+        let mut data = Vec::new();
+        data.put_var_bytes(&[7; 10000]);
+        assert_eq!(10000, ByteSource(&data, 0).get_var_bytes_into(&mut buffer)?);
+        assert_eq!(vec![7u8; 10000], buffer);
+        Ok(())
+    }
+
+    #[test]
+    fn test_nanos_delta() -> Result<()> {
+        let deltas = [0i64, -1, 1, -32, 31, -1_500_000, 2_000_000_000, i64::MIN, i64::MAX];
+        let mut data = Vec::new();
+        for d in deltas { data.put_nanos_delta(d); }
+        assert_eq!(vec![0u8, 1 << 2, 2 << 2, 63 << 2], data[..4].to_vec());
+        let mut src = SliceSource::from(&data);
+        for d in deltas { assert_eq!(d, src.get_nanos_delta()?); }
+        Ok(())
+    }
+
+    #[test]
+    fn test_borrowing_source() -> Result<()> {
+        fn read_pair<'a>(source: &mut impl BorrowingSource<'a>) -> Result<(&'a [u8], &'a str)> {
+            Ok((source.get_var_bytes_ref()?, source.get_str_ref()?))
+        }
+        let mut data = Vec::new();
+        data.put_var_bytes(&[1, 2, 3]);
+        data.put_str("zero copy");
+        data.put_var_bytes(&[0xC3, 0x28]);
+        let (bytes, text) = {
+            let mut src = SliceSource::from(&data);
+            let pair = read_pair(&mut src)?;
+            assert!(matches!(src.get_str_ref(), Err(BipackError::BadEncoding(_))));
+            pair
+        };
+        assert_eq!(&[1u8, 2, 3], bytes);
+        assert_eq!(data[1..].as_ptr(), bytes.as_ptr());
+        assert_eq!("zero copy", text);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsigned_slice() -> Result<()> {
+        let values: Vec<u64> = (0..100).map(|i| i * 3).chain([70000, u64::MAX]).collect();
+        let mut data = Vec::new();
+        data.put_unsigned_slice(&values);
+        let mut fixed = Vec::new();
+        for x in &values { fixed.put_u64(*x); }
+        assert!(data.len() * 4 < fixed.len(), "{} vs {}", data.len(), fixed.len());
+        assert_eq!(values, SliceSource::from(&data).get_unsigned_vec()?);
+
+        let mut small = Vec::new();
+        small.put_unsigned_slice(&[1u16, 2, 300]);
+        assert_eq!(vec![1u64, 2, 300], SliceSource::from(&small).get_unsigned_vec()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sorted_unsigned() -> Result<()> {
+        let ids = [100_000u64, 100_001, 100_005, 100_200, 100_200];
+        let mut data = Vec::new();
+        data.put_sorted_unsigned(&ids);
+        let mut naive = Vec::new();
+        naive.put_unsigned_slice(&ids);
+        assert_eq!(9, data.len());
+        assert!(data.len() < naive.len());
+        assert_eq!(ids.to_vec(), SliceSource::from(&data).get_sorted_unsigned()?);
+
+        let mut small = Vec::new();
+        small.put_sorted_unsigned(&[100, 101, 105, 200]);
+        let mut small_naive = Vec::new();
+        small_naive.put_unsigned_slice(&[100u64, 101, 105, 200]);
+        assert!(small.len() < small_naive.len());
+        assert_eq!(vec![100u64, 101, 105, 200], SliceSource::from(&small).get_sorted_unsigned()?);
+
+        let mut overflow = Vec::new();
+        overflow.put_unsigned_slice(&[2, u64::MAX, 1]);
+        assert!(matches!(SliceSource::from(&overflow).get_sorted_unsigned(), Err(BipackError::Overflow)));
+        assert!(std::panic::catch_unwind(|| Vec::new().put_sorted_unsigned(&[2, 1])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_result_packing() -> Result<()> {
+        type Outcome = std::result::Result<u32, String>;
+        let data = bipack!(Ok::<u32, String>(5), Err::<u32, String>("boom".to_string()));
+        assert_eq!(vec![0u8, 5 << 2, 1, 4 << 2, b'b', b'o', b'o', b'm'], data);
+        let mut src = SliceSource::from(&data);
+        assert_eq!(Ok(5), Outcome::bi_unpack(&mut src)?);
+        assert_eq!(Err("boom".to_string()), Outcome::bi_unpack(&mut src)?);
+        let bad = Outcome::bi_unpack(&mut SliceSource::from(&[2, 0]));
+        assert!(matches!(bad, Err(BipackError::UnknownVariant(2))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_seal() -> Result<()> {
+        let data = bipack!("sealed", 42u32);
+        let sealed = seal(&data);
+        assert_eq!(data.len() + 4, sealed.len());
+        assert_eq!(&data[..], unseal(&sealed)?);
+        for bit in 0..sealed.len() * 8 {
+            let mut broken = sealed.clone();
+            broken[bit / 8] ^= 1 << (bit % 8);
+            assert!(matches!(unseal(&broken), Err(BipackError::ChecksumMismatch)));
+        }
+        assert!(matches!(unseal(&[1, 2]), Err(BipackError::NoDataError { offset: 2, needed: 2 })));
+        assert_eq!(&[] as &[u8], unseal(&seal(&[]))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fixed_point() -> Result<()> {
+        let values = [(0i64, 0u8), (12345, 2), (-12345, 2), (-1, 18), (i64::MAX, 18), (i64::MIN, 0)];
+        let mut data = Vec::new();
+        for (mantissa, scale) in values { data.put_fixed_point(mantissa, scale); }
+        let mut src = SliceSource::from(&data);
+        for v in values { assert_eq!(v, src.get_fixed_point()?); }
+        src.finish()?;
+
+        data.clear();
+        data.put_fixed_point(-3, 18);
+        assert_eq!(vec![5 << 2, 18], data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_rest() -> Result<()> {
+        let mut data = bipack!(7u32, "head");
+        data.extend_from_slice(b"payload");
+
+        let mut src = SliceSource::from(&data);
+        assert_eq!(7, src.get_unsigned()?);
+        assert_eq!("head", src.get_str()?);
+        assert_eq!(b"payload".to_vec(), src.get_rest()?);
+        assert_eq!(0, src.remaining());
+        assert_eq!(Vec::<u8>::new(), src.get_rest()?);
+        src.finish()?;
+
+        let mut src = SliceSource::from(&data);
+        src.skip(6)?;
+        assert_eq!(b"payload", src.get_rest_borrowed());
+        assert_eq!(0, src.remaining());
+
+        struct ByteSource<'a>(&'a [u8], usize);
+        impl BipackSource for ByteSource<'_> {
+            fn get_u8(self: &mut Self) -> Result<u8> {
+                let b = *self.0.get(self.1).ok_or(BipackError::NoDataError { offset: self.1, needed: 1 })?;
+                self.1 += 1;
+                Ok(b)
+            }
+        }
+        let mut src = ByteSource(&data, 1);
+        assert_eq!("head", src.get_str()?);
+        assert_eq!(b"payload".to_vec(), src.get_rest()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact() -> Result<()> {
+        assert_eq!("", to_compact(&[]));
+        assert_eq!("-_8", to_compact(&[0xFB, 0xFF]));
+        assert_eq!("AQID_w", to_compact(&[1, 2, 3, 0xFF]));
+        let data = bipack!("compact text", 0x1020304050u64, [0xFFu8; 5]);
+        let text = to_compact(&data);
+        assert!(text.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_'));
+        assert_eq!(data, from_compact(&text)?);
+        use base64::Engine;
+        assert_eq!(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&data), text);
+        for n in 0..8 {
+            let data: Vec<u8> = (0..n).map(|i| (i * 37 + 200) as u8).collect();
+            assert_eq!(data, from_compact(&to_compact(&data))?);
+        }
+        for bad in ["A", "AQID_w==", "AQ+D", "AR"] {
+            assert!(matches!(from_compact(bad), Err(BipackError::InvalidValue)), "{}", bad);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_pull_decoder() -> Result<()> {
+        use crate::schema::{Field, Schema};
+        let schema = Schema(vec![Field::FixedU16, Field::SmartUint, Field::SmartInt, Field::Str,
+            Field::Array(Box::new(Field::VarBytes)), Field::Array(Box::new(Field::FixedU8)),
+            Field::FixedU64]);
+        let mut data = Vec::new();
+        data.put_u16(513);
+        data.put_unsigned(1u64 << 40);
+        data.put_signed(-300);
+        data.put_str("streamed");
+        data.put_unsigned(2u8);
+        data.put_var_bytes(&[1, 2, 3]);
+        data.put_var_bytes(&[]);
+        data.put_unsigned(0u8);
+        data.put_u64(7);
+        let expected = vec![Event::Unsigned(513), Event::Unsigned(1 << 40), Event::Signed(-300),
+            Event::Str("streamed".to_string()), Event::ArrayStart(2), Event::Bytes(vec![1, 2, 3]),
+            Event::Bytes(vec![]), Event::ArrayEnd, Event::ArrayStart(0), Event::ArrayEnd,
+            Event::Unsigned(7)];
+
+        let mut decoder = PullDecoder::new(schema.clone());
+        assert_eq!(expected, decoder.feed(&data)?);
+        assert!(decoder.is_done());
+
+        // byte by byte, so every smartint is split
+        let mut decoder = PullDecoder::new(schema.clone());
+        let mut events = Vec::new();
+        for b in &data {
+            assert!(!decoder.is_done());
+            events.extend(decoder.feed(&[*b])?);
+        }
+        assert_eq!(expected, events);
+        assert!(decoder.is_done());
+
+        let mut decoder = PullDecoder::new(schema);
+        assert_eq!(vec![Event::Unsigned(513)], decoder.feed(&data[..4])?);
+        data.push(0);
+        let tail = decoder.feed(&data[4..]);
+        assert!(matches!(tail, Err(BipackError::TrailingData { remaining: 1 })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_str_lossy() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_var_bytes(b"ok \xFF\xFE end");
+        data.put_str("valid");
+        assert!(matches!(SliceSource::from(&data).get_str(), Err(BipackError::BadEncoding(_))));
+        let mut src = SliceSource::from(&data);
+        assert_eq!("ok \u{FFFD}\u{FFFD} end", src.get_str_lossy()?);
+        assert_eq!("valid", src.get_str_lossy()?);
+        src.finish()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_nested_containers() -> Result<()> {
+        type Sparse = Option<Vec<Option<String>>>;
+        let values: Vec<Sparse> = vec![
+            None,
+            Some(vec![]),
+            Some(vec![Some("a".to_string()), None, Some(String::new())]),
+        ];
+        let data = bipack!(values);
+        assert_eq!(vec![3 << 2, 0, 1, 0, 1, 3 << 2, 1, 1 << 2, b'a', 0, 1, 0], data);
+        assert_eq!(values, from_slice_exact::<Vec<Sparse>>(&data)?);
+
+        let bytes = bipack!(vec![1u8, 2, 3], Some(vec![Some(7u32), None]));
+        let mut src = SliceSource::from(&bytes);
+        assert_eq!(vec![1u8, 2, 3], src.get_var_bytes()?);
+        assert_eq!(Some(vec![Some(7u32), None]), Option::<Vec<Option<u32>>>::bi_unpack(&mut src)?);
+        src.finish()?;
+        let bad = Option::<u8>::bi_unpack(&mut SliceSource::from(&[2, 0]));
+        assert!(matches!(bad, Err(BipackError::InvalidValue)));
+
+        // scalar u8 is a smartint both ways, values above 63 take 2 bytes
+        let small: Option<Vec<Option<u8>>> = Some(vec![Some(200), None, Some(64), Some(5)]);
+        assert_eq!(small, from_slice_exact(&bipack!(small))?);
+        assert_eq!(Some(255u8), from_slice_exact(&bipack!(Some(255u8)))?);
+        let result: core::result::Result<u8, u8> = Err(128);
+        assert_eq!(result, from_slice_exact(&bipack!(result))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_signed_compact() -> Result<()> {
+        fn packed(value: i64) -> Vec<u8> {
+            let mut data = Vec::new();
+            data.put_signed_compact(value);
+            data
+        }
+        assert_eq!(vec![2 << 2], packed(-1));
+        assert_eq!(3, packed(-1_000_000).len());
+        assert_eq!(vec![(8 << 1 | 1) << 2, 0x80, 0, 0, 0, 0, 0, 0, 0], packed(i64::MIN));
+        for value in [0, 1, -1, 63, -64, 1000, -1_000_000, i32::MIN as i64, i64::MAX / 2,
+            -(1 << 62), i64::MAX, i64::MIN] {
+            let data = packed(value);
+            assert!(data.len() <= 9, "{}", value);
+            if value != i64::MIN {
+                let mut signed = Vec::new();
+                signed.put_signed(value);
+                assert!(data.len() <= signed.len(), "{}", value);
+            }
+            let mut src = SliceSource::from(&data);
+            assert_eq!(value, src.get_signed_compact()?);
+            src.finish()?;
+        }
+        let bad = SliceSource::from(&[(3 << 1 | 1) << 2, 0, 0, 0]).get_signed_compact();
+        assert!(matches!(bad, Err(BipackError::InvalidValue)));
+        Ok(())
+    }
+
+    // Natively only the success paths could be checked: creating JS errors needs a JS host.
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_wasm_codec() {
+        use crate::wasm_codec::WasmCodec;
+        let data = WasmCodec::encode_u64(24573);
+        assert_eq!(vec![0xF6, 0x7F, 0x01], data);
+        assert_eq!(24573, WasmCodec::decode_u64(&data).unwrap());
+        assert_eq!("wasm", WasmCodec::decode_str(&WasmCodec::encode_str("wasm")).unwrap());
+        assert_eq!(vec![1, 2], WasmCodec::decode_bytes(&WasmCodec::encode_bytes(&[1, 2])).unwrap());
+    }
+
+    #[test]
+    fn test_unsigned_u128() -> Result<()> {
+        let values = [0u128, V0LIMIT as u128 - 1, V0LIMIT as u128, V1LIMIT as u128 - 1,
+            V1LIMIT as u128, V2LIMIT as u128 - 1, V2LIMIT as u128, u64::MAX as u128,
+            u64::MAX as u128 + 1, 1 << 105, (1 << 106) - 1, 1 << 106, u128::MAX];
+        for value in values {
+            let mut data = Vec::new();
+            data.put_unsigned_u128(value);
+            if value <= u64::MAX as u128 {
+                let mut small = Vec::new();
+                small.put_unsigned(value as u64);
+                assert_eq!(small, data);
+            }
+            assert!(data.len() <= 19);
+            let mut src = SliceSource::from(&data);
+            assert_eq!(value, src.get_unsigned_u128()?, "{}", value);
+            src.finish()?;
+        }
+        let mut data = Vec::new();
+        data.put_unsigned_u128(u128::MAX);
+        assert_eq!(19, data.len());
+        // one more bit than fits
+        *data.last_mut().unwrap() = 0x7F;
+        assert!(matches!(SliceSource::from(&data).get_unsigned_u128(), Err(BipackError::Overflow)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_str_utf16() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_str_utf16("Ok\u{1F600}");
+        // the emoji is a surrogate pair
+        assert_eq!("104f006b003dd800de", to_hex(&data));
+        data.put_str_utf16("");
+        let mut src = SliceSource::from(&data);
+        assert_eq!("Ok\u{1F600}", src.get_str_utf16()?);
+        assert_eq!("", src.get_str_utf16()?);
+        src.finish()?;
+
+        let mut bad = Vec::new();
+        bad.put_unsigned(1u8);
+        bad.put_u16_le(0xD83D);
+        assert!(matches!(SliceSource::from(&bad).get_str_utf16(), Err(BipackError::InvalidValue)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_peek_var_len() -> Result<()> {
+        let mut data = Vec::new();
+        data.put_u8(7);
+        data.put_var_bytes(&[0x55; 100]);
+        let mut src = SliceSource::from(&data);
+        src.get_u8()?;
+        assert_eq!((100, 2), src.peek_var_len()?);
+        assert_eq!(1, src.position());
+        assert_eq!(vec![0x55; 100], src.get_var_bytes()?);
+        src.finish()?;
+
+        let mut src = SliceSource::from(&[0x03, 0xFF]);
+        assert!(matches!(src.peek_var_len(), Err(BipackError::NoDataError { .. })));
+        assert_eq!(0, src.position());
+        Ok(())
+    }
+
+    #[test]
+    fn test_interned_strings() -> Result<()> {
+        let labels = ["active", "deleted", "active", "active", "deleted", "pending"];
+        let mut sink = DictSink::new(Vec::new());
+        let mut sizes = Vec::new();
+        for (i, label) in labels.iter().enumerate() {
+            let start = sink.get_ref().len();
+            sink.put_interned_str(label);
+            sizes.push(sink.get_ref().len() - start);
+            sink.put_unsigned(i);
+        }
+        // the second occurrence is only the index
+        assert_eq!(vec![8, 9, 1, 1, 1, 9], sizes);
+        let data = sink.into_inner();
+
+        let mut src = DictSource::new(SliceSource::from(&data));
+        for (i, label) in labels.iter().enumerate() {
+            assert_eq!(*label, src.get_interned_str()?);
+            assert_eq!(i as u64, src.get_unsigned()?);
+        }
+        src.into_inner().finish()?;
+
+        let bad = DictSource::new(SliceSource::from(&[1 << 2, 0])).get_interned_str();
+        assert!(matches!(bad, Err(BipackError::InvalidValue)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_unsigned_incremental() -> Result<()> {
+        // one value of each size class, the last one has a 2-byte varint tail
+        for value in [1u64, V0LIMIT, V1LIMIT, V2LIMIT << 7] {
+            let mut data = Vec::new();
+            data.put_unsigned(value);
+            for received in 0..data.len() {
+                let mut src = SliceSource::from(&data[..received]);
+                match src.get_unsigned_incremental() {
+                    Err(BipackError::Incomplete { needed }) => {
+                        // the type is unknown until the first byte, the tail length until
+                        // its last byte
+                        let expected = match received {
+                            0 => 1,
+                            _ if value < V2LIMIT => data.len() - received,
+                            _ => 4usize.saturating_sub(received).max(1),
+                        };
+                        assert_eq!(expected, needed, "{} {}", value, received);
+                    }
+                    other => panic!("{} {}: {:?}", value, received, other),
+                }
+                assert_eq!(0, src.position());
+            }
+            let mut src = SliceSource::from(&data);
+            assert_eq!(value, src.get_unsigned_incremental()?);
+            src.finish()?;
+        }
+        // malformed, not incomplete, data are reported as usual
+        let mut src = SliceSource::strict(&[0x01, 0x00]);
+        assert!(matches!(src.get_unsigned_incremental(), Err(BipackError::NonCanonical)));
+        Ok(())
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn test_var_bytes_compressed() -> Result<()> {
+        let big: Vec<u8> = (0..10240).map(|i| (i % 16) as u8).collect();
+        let small = b"short".to_vec();
+        let mut data = Vec::new();
+        data.put_var_bytes_compressed(&big);
+        assert_eq!(1, data[0]);
+        assert!(data.len() < 500, "{}", data.len());
+        let start = data.len();
+        data.put_var_bytes_compressed(&small);
+        // stored as is, only the flag is added
+        assert_eq!(vec![0, 5 << 2, b's', b'h', b'o', b'r', b't'], data[start..]);
+
+        let mut src = SliceSource::from(&data);
+        assert_eq!(big, src.get_var_bytes_compressed()?);
+        assert_eq!(small, src.get_var_bytes_compressed()?);
+        src.finish()?;
+        let bad = SliceSource::from(&[2, 0]).get_var_bytes_compressed();
+        assert!(matches!(bad, Err(BipackError::InvalidValue)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_u64_map() -> Result<()> {
+        let entries = [(1000u64, vec![1u8, 2]), (1, vec![]), (u64::MAX, vec![0xFF; 70]), (64, vec![3])];
+        let forward: BTreeMap<u64, Vec<u8>> = entries.iter().cloned().collect();
+        let backward: BTreeMap<u64, Vec<u8>> = entries.iter().rev().cloned().collect();
+        let mut data = Vec::new();
+        data.put_u64_map(&forward);
+        let mut other = Vec::new();
+        other.put_u64_map(&backward);
+        assert_eq!(data, other);
+        let mut generic = Vec::new();
+        generic.put_map_sorted(&forward);
+        assert_eq!(data, generic);
+
+        let mut src = SliceSource::from(&data);
+        assert_eq!(forward, src.get_u64_map()?);
+        src.finish()?;
+
+        let mut unsorted = Vec::new();
+        unsorted.put_unsigned(2u8);
+        unsorted.put_unsigned(5u8);
+        unsorted.put_var_bytes(&[]);
+        unsorted.put_unsigned(5u8);
+        unsorted.put_var_bytes(&[]);
+        assert!(matches!(SliceSource::from(&unsorted).get_u64_map(), Err(BipackError::NonCanonical)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dump_highlight() {
+        let data: Vec<u8> = (0..40).collect();
+        assert_eq!(to_dump(&data), to_dump_highlight(&data, 0..0));
+        let dump = to_dump_highlight(&data, 0x13..0x15);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(to_dump(&data).lines().next(), Some(lines[0]));
+        assert_eq!("0010 10 11 12>13 14<15 16 17 18 19 1a 1b 1c 1d 1e 1f |................|", lines[1]);
+        // the marker is right before the hex column of the first highlighted byte
+        assert_eq!(5 + 3 * 3 - 1, lines[1].find('>').unwrap());
+
+        let dump = to_dump_highlight(&data, 0x0F..0x21);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert!(lines[0].starts_with("0000 00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e>0f<|"));
+        assert!(lines[1].starts_with("0010>10 11 12 13 14 15 16 17 18 19 1a 1b 1c 1d 1e 1f<|"));
+        assert!(lines[2].starts_with("0020>20<21 22"));
+        let tail = format!("26>27<{}|", " ".repeat(24));
+        assert!(to_dump_highlight(&data, 0x27..0x30).contains(&tail));
+    }
+
+    #[test]
+    fn test_dump_to_io() -> std::io::Result<()> {
+        for size in [0, 1, 15, 16, 17, 41, 1000] {
+            let data: Vec<u8> = (0..size).map(|i| (i * 13) as u8).collect();
+            let mut out = Vec::new();
+            dump_to_io(&data, &mut out)?;
+            assert_eq!(to_dump(&data).as_bytes(), out.as_slice());
+        }
+        Ok(())
+    }
+}
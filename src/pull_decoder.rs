@@ -0,0 +1,122 @@
+// Copyright 2023 by Sergey S. Chernov.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::bipack_source::{BipackError, BipackSource, Result, SliceSource};
+use crate::schema::{Field, Schema};
+
+/// Decoded field reported by [PullDecoder].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// Any unsigned field, fixed size or smartint.
+    Unsigned(u64),
+    /// [Field::SmartInt] field.
+    Signed(i64),
+    /// [Field::VarBytes] field.
+    Bytes(Vec<u8>),
+    /// [Field::Str] field.
+    Str(String),
+    /// Array (sorted or not) with the given number of elements, which are reported next,
+    /// followed by [Event::ArrayEnd].
+    ArrayStart(u64),
+    ArrayEnd,
+}
+
+/// Push-style decoder of a message described by a [Schema]: feed it bytes as they arrive,
+/// e.g. from a socket, and get events for completely decoded fields, without buffering the
+/// whole message.
+///
+/// A field split across [PullDecoder::feed] calls, including a partial smartint (its first
+/// byte tells the size, but the rest is not here yet), is kept in the internal buffer and is
+/// decoded from its start when more bytes are fed. So the decoder buffers at most one field;
+/// note that it means a whole binary or string field. Sorted arrays order is not checked,
+/// use [crate::schema::is_canonical] for it.
+pub struct PullDecoder {
+    schema: Schema,
+    /// Index of the next top-level field to decode.
+    next: usize,
+    /// Element types and remaining counts of arrays being decoded, innermost last.
+    arrays: Vec<(Field, u64)>,
+    pending: Vec<u8>,
+}
+
+impl PullDecoder {
+    pub fn new(schema: Schema) -> PullDecoder {
+        PullDecoder { schema, next: 0, arrays: Vec::new(), pending: Vec::new() }
+    }
+
+    /// Decode as many fields as possible with the bytes fed so far and return their events.
+    /// Bytes beyond the last field of the schema cause [BipackError::TrailingData]. After
+    /// any error the decoder state is unspecified and it should be dropped.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<Vec<Event>> {
+        self.pending.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        let mut consumed = 0;
+        let result = loop {
+            if let Some((_, 0)) = self.arrays.last() {
+                self.arrays.pop();
+                events.push(Event::ArrayEnd);
+                continue;
+            }
+            let field = match self.arrays.last() {
+                Some((item, _)) => item.clone(),
+                None if self.next < self.schema.0.len() => self.schema.0[self.next].clone(),
+                None if consumed < self.pending.len() =>
+                    break Err(BipackError::TrailingData { remaining: self.pending.len() - consumed }),
+                None => break Ok(()),
+            };
+            let mut source = SliceSource::from(&self.pending[consumed..]);
+            match decode_field(&mut source, &field) {
+                Ok(event) => {
+                    consumed += source.position();
+                    match self.arrays.last_mut() {
+                        Some((_, remaining)) => *remaining -= 1,
+                        None => self.next += 1,
+                    }
+                    if let (Event::ArrayStart(count), Field::Array(item) | Field::SortedArray(item)) =
+                        (&event, field) {
+                        self.arrays.push((*item, *count));
+                    }
+                    events.push(event);
+                }
+                Err(BipackError::NoDataError { .. }) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+        self.pending.drain(..consumed);
+        result.map(|_| events)
+    }
+
+    /// True when all fields of the schema are decoded.
+    pub fn is_done(&self) -> bool {
+        self.next == self.schema.0.len() && self.arrays.is_empty()
+    }
+}
+
+/// Decode a primitive field or the array size.
+fn decode_field(source: &mut SliceSource, field: &Field) -> Result<Event> {
+    Ok(match field {
+        Field::FixedU8 => Event::Unsigned(source.get_u8()? as u64),
+        Field::FixedU16 => Event::Unsigned(source.get_u16()? as u64),
+        Field::FixedU32 => Event::Unsigned(source.get_u32()? as u64),
+        Field::FixedU64 => Event::Unsigned(source.get_u64()?),
+        Field::SmartUint => Event::Unsigned(source.get_unsigned()?),
+        Field::SmartInt => Event::Signed(source.get_signed()?),
+        Field::VarBytes => Event::Bytes(source.get_var_bytes()?),
+        Field::Str => Event::Str(source.get_str()?),
+        Field::Array(_) | Field::SortedArray(_) => Event::ArrayStart(source.get_unsigned()?),
+    })
+}
@@ -0,0 +1,163 @@
+// Copyright 2023 by Sergey S. Chernov.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::bipack_source::{BipackError, BipackSource, Result, SliceSource};
+
+/// Kind of packed field. As bipack does not store field names or types, this is
+/// what one needs to walk a packed message without decoding it into rust types.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    FixedU8,
+    FixedU16,
+    FixedU32,
+    FixedU64,
+    /// `smartint` unsigned, see [crate::bipack_sink::BipackSink::put_unsigned].
+    SmartUint,
+    /// `smartint` signed, see [crate::bipack_sink::BipackSink::put_signed].
+    SmartInt,
+    /// Variable-length binary, see [crate::bipack_sink::BipackSink::put_var_bytes].
+    VarBytes,
+    /// Variable-length UTF-8 string, see [crate::bipack_sink::BipackSink::put_str].
+    Str,
+    /// Smartint-encoded count followed by that many elements.
+    Array(Box<Field>),
+    /// Same as [Field::Array] but elements must be in strictly ascending order, as sets and
+    /// map keys should be when the encoding is canonical: numeric order for integer
+    /// elements, as the crate encoders write them, and order of packed bytes otherwise.
+    SortedArray(Box<Field>),
+}
+
+/// Layout of a whole packed message: its fields in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema(pub Vec<Field>);
+
+/// Check that `data` is a canonical encoding of a message described by `schema`: all
+/// smartints, including sizes, are in the shortest possible form, sorted arrays are
+/// strictly ascending and there is no trailing data. Only canonical encoding gives
+/// one-to-one mapping of values to bytes, which matters when packed data are signed or
+/// hashed.
+///
+/// Returns error if data does not fit the schema at all, e.g. is truncated.
+pub fn is_canonical(data: &[u8], schema: &Schema) -> Result<bool> {
+    let mut source = SliceSource::from(data);
+    for field in &schema.0 {
+        if !check_canonical(&mut source, data, field)? { return Ok(false); }
+    }
+    Ok(source.position() == data.len())
+}
+
+fn check_canonical(source: &mut SliceSource, data: &[u8], field: &Field) -> Result<bool> {
+    match field {
+        Field::FixedU8 => { source.get_u8()?; }
+        Field::FixedU16 => { source.get_u16()?; }
+        Field::FixedU32 => { source.get_u32()?; }
+        Field::FixedU64 => { source.get_u64()?; }
+        Field::SmartUint => return Ok(canonical_unsigned(source)?.is_some()),
+        // 1 is the "negative zero"
+        Field::SmartInt => return Ok(matches!(canonical_unsigned(source)?, Some(x) if x != 1)),
+        Field::VarBytes => {
+            let Some(size) = canonical_unsigned(source)? else { return Ok(false); };
+            source.get_fixed_bytes(size as usize)?;
+        }
+        Field::Str => {
+            let Some(size) = canonical_unsigned(source)? else { return Ok(false); };
+            String::from_utf8(source.get_fixed_bytes(size as usize)?)
+                .map_err(BipackError::BadEncoding)?;
+        }
+        Field::Array(item) | Field::SortedArray(item) => {
+            let Some(count) = canonical_unsigned(source)? else { return Ok(false); };
+            let sorted = matches!(field, Field::SortedArray(_));
+            let mut last: Option<SortKey> = None;
+            for i in 0..count {
+                let start = source.position();
+                if !check_canonical(source, data, item)? { return Ok(false); }
+                if !sorted { continue; }
+                let current = sort_key(item, &data[start..source.position()])?;
+                if last.is_some_and(|l| l >= current) { return Ok(false); }
+                last = Some(current);
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// Value [Field::SortedArray] elements are ordered by.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum SortKey<'a> {
+    Number(i128),
+    Packed(&'a [u8]),
+}
+
+/// Sort key of an already checked element packed in `packed`.
+fn sort_key<'a>(item: &Field, packed: &'a [u8]) -> Result<SortKey<'a>> {
+    let mut source = SliceSource::from(packed);
+    Ok(SortKey::Number(match item {
+        Field::FixedU8 => source.get_u8()? as i128,
+        Field::FixedU16 => source.get_u16()? as i128,
+        Field::FixedU32 => source.get_u32()? as i128,
+        Field::FixedU64 => source.get_u64()? as i128,
+        Field::SmartUint => source.get_unsigned()? as i128,
+        Field::SmartInt => source.get_signed()? as i128,
+        _ => return Ok(SortKey::Packed(packed)),
+    }))
+}
+
+/// Cost of decoding a field or an array element in addition to its bytes, see [decode_cost].
+pub const FIELD_COST: u64 = 4;
+
+/// Estimate the cost of decoding a message described by `schema` as the number of bytes
+/// to read plus [FIELD_COST] for each field and array element. Values are skipped, not
+/// decoded, so smart contracts can charge gas before decoding. Returns error if data does
+/// not fit the schema.
+pub fn decode_cost(data: &[u8], schema: &Schema) -> Result<u64> {
+    let mut source = SliceSource::from(data);
+    let mut fields = 0;
+    for field in &schema.0 { fields += skip_field(&mut source, field)?; }
+    Ok(source.position() as u64 + fields * FIELD_COST)
+}
+
+/// Skip the field, returning number of fields skipped including array elements.
+fn skip_field(source: &mut SliceSource, field: &Field) -> Result<u64> {
+    match field {
+        Field::FixedU8 => source.skip(1)?,
+        Field::FixedU16 => source.skip(2)?,
+        Field::FixedU32 => source.skip(4)?,
+        Field::FixedU64 => source.skip(8)?,
+        Field::SmartUint | Field::SmartInt => { source.get_unsigned()?; }
+        Field::VarBytes | Field::Str => {
+            let size = source.get_unsigned()? as usize;
+            source.skip(size)?;
+        }
+        Field::Array(item) | Field::SortedArray(item) => {
+            let count = source.get_unsigned()?;
+            let mut fields = 1;
+            for i in 0..count { fields += skip_field(source, item)?; }
+            return Ok(fields);
+        }
+    }
+    Ok(1)
+}
+
+/// Read smartint, returning `None` if it is not packed in the shortest possible form.
+fn canonical_unsigned(source: &mut impl BipackSource) -> Result<Option<u64>> {
+    match source.get_unsigned_canonical() {
+        Ok(x) => Ok(Some(x)),
+        Err(BipackError::NonCanonical) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
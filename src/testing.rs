@@ -0,0 +1,70 @@
+// Copyright 2023 by Sergey S. Chernov.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Property checks to test (and fuzz) custom sinks and sources against the reference
+//! [`Vec<u8>`] sink and [SliceSource]. Available with `testing` feature.
+
+use alloc::vec::Vec;
+
+use crate::bipack_sink::BipackSink;
+use crate::bipack_source::{BipackSource, Result, SliceSource};
+
+/// Pack `value` with [BipackSink::put_unsigned] and check it unpacks to the same value.
+pub fn roundtrip_unsigned(value: u64) -> bool {
+    let mut data = Vec::new();
+    data.put_unsigned(value);
+    SliceSource::from(&data).get_unsigned().is_ok_and(|x| x == value)
+}
+
+/// Pack `value` with [BipackSink::put_signed] and check it unpacks to the same value.
+pub fn roundtrip_signed(value: i64) -> bool {
+    let mut data = Vec::new();
+    data.put_signed(value);
+    SliceSource::from(&data).get_signed().is_ok_and(|x| x == value)
+}
+
+/// Pack `value` with [BipackSink::put_str] and check it unpacks to the same value.
+pub fn roundtrip_str(value: &str) -> bool {
+    let mut data = Vec::new();
+    data.put_str(value);
+    SliceSource::from(&data).get_str().is_ok_and(|x| x == value)
+}
+
+/// Check that the sink created by `make_sink` packs `value` with
+/// [BipackSink::put_unsigned] exactly as the reference sink does. `into_bytes` returns
+/// what was written to the sink.
+pub fn sink_matches_reference<S: BipackSink>(
+    make_sink: impl FnOnce() -> S,
+    into_bytes: impl FnOnce(S) -> Vec<u8>,
+    value: u64,
+) -> bool {
+    let mut reference = Vec::new();
+    reference.put_unsigned(value);
+    let mut sink = make_sink();
+    sink.put_unsigned(value);
+    into_bytes(sink) == reference
+}
+
+/// Check that `decode`, which should create the source from given bytes and call
+/// [BipackSource::get_unsigned] on it, unpacks the packed `value` exactly as [SliceSource]
+/// does, including errors on truncated data.
+pub fn source_matches_reference(decode: impl Fn(&[u8]) -> Result<u64>, value: u64) -> bool {
+    let mut data = Vec::new();
+    data.put_unsigned(value);
+    for size in 0..=data.len() {
+        let expected = SliceSource::from(&data[..size]).get_unsigned().ok();
+        if decode(&data[..size]).ok() != expected { return false; }
+    }
+    true
+}
@@ -14,7 +14,13 @@
 
 // use string_builder::Builder;
 
-use std::string::FromUtf8Error;
+use alloc::format;
+use alloc::string::{FromUtf8Error, String};
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::bipack_source::{self, BipackSource, SliceSource};
+use crate::schema::{Field, Schema};
 
 /// Absolutely minimalistic string builder (growing string implemented minimal and
 /// more or less effective). Just to avoid dependencies for better .wasm usage.
@@ -48,38 +54,249 @@ impl StringBuilder {
 /// 0030 30 31                                           |01              |
 ///```
 pub fn to_dump(data: &[u8]) -> String {
-    let mut offset = 0usize;
-    let mut counter = 0;
+    to_dump_highlight(data, 0..0)
+}
+
+/// Same as [to_dump] but bytes in `range`, e.g. where decoding failed, are enclosed with
+/// `>` and `<` in place of spaces in the hex column, so columns stay aligned:
+/// ```text
+/// 0010 10 11 12>13 14<15 16 17 18 19 1a 1b 1c 1d 1e 1f |................|
+///```
+/// The range spanning several rows is marked in each of them.
+pub fn to_dump_highlight(data: &[u8], highlight: Range<usize>) -> String {
+    let mut result = StringBuilder::new();
+    for (index, row) in data.chunks(16).enumerate() {
+        dump_row(&mut result, index * 16, row, &highlight);
+    }
+    result.string().unwrap()
+}
+
+/// Write the dump of `data`, the same as [to_dump] returns, directly to `out` row by row,
+/// without building the whole text, e.g. to dump huge files to stdout.
+#[cfg(feature = "std")]
+pub fn dump_to_io<W: std::io::Write>(data: &[u8], out: &mut W) -> std::io::Result<()> {
+    let mut row_text = StringBuilder::new();
+    for (index, row) in data.chunks(16).enumerate() {
+        row_text.0.clear();
+        dump_row(&mut row_text, index * 16, row, &(0..0));
+        out.write_all(&row_text.0)?;
+    }
+    Ok(())
+}
+
+/// Append one line of the dump: the row of up to 16 bytes starting at `start`.
+fn dump_row(result: &mut StringBuilder, start: usize, row: &[u8], highlight: &Range<usize>) {
+    let marked = |i: usize| i < row.len() && highlight.contains(&(start + i));
+    result.append(format!("{:04X}", start));
+    // separator before i-th byte, also after the last one, marks highlighted bytes
+    for i in 0..=row.len() {
+        let separator = match (i > 0 && marked(i - 1), marked(i)) {
+            (false, true) => '>',
+            (true, false) => '<',
+            _ => ' ',
+        };
+        result.append_char(separator);
+        if let Some(b) = row.get(i) { result.append(format!("{:02x}", b)); }
+    }
+    ascii_dump(result, row);
+}
+
+fn ascii_dump(result: &mut StringBuilder, row: &[u8]) {
+    for i in row.len()..16 { result.append("   "); }
+    result.append("|");
+    for b in row {
+        if (32..=127).contains(b) {
+            result.append_char(*b as char)
+        } else {
+            result.append_char('.');
+        }
+    }
+    for i in row.len()..16 { result.append_char(' '); }
+    result.append("|\n");
+}
+
+/// Decode `data` as described by `schema` into a human-readable breakdown, one field
+/// per line with its offset, index and kind, like:
+/// ```text
+/// 0000 #0 FixedU32 = 1234
+/// 0004 #1 Str = "hello"
+/// 000A #2 Array[2]
+/// 000B   #2.0 SmartUint = 1
+/// 000C   #2.1 SmartUint = 2
+/// ```
+/// Fails with the decoding error at the first field that does not fit the data. Trailing
+/// data, if any, are reported in the last line. Use it to debug interop issues.
+pub fn describe(data: &[u8], schema: &Schema) -> bipack_source::Result<String> {
+    let mut source = SliceSource::from(data);
     let mut result = StringBuilder::new();
+    for (i, field) in schema.0.iter().enumerate() {
+        describe_field(&mut source, field, &format!("#{}", i), 0, &mut result)?;
+    }
+    if source.position() < data.len() {
+        result.append(format!("{:04X} {} trailing byte(s)\n", source.position(),
+                              data.len() - source.position()));
+    }
+    Ok(result.string().unwrap())
+}
 
-    fn ascii_dump(result: &mut StringBuilder, counter: usize, data: &[u8], offset: usize) {
-        for i in counter..16 { result.append("   "); }
-        result.append("|");
-        for i in 0..counter {
-            let b = data[offset - counter + i];
-            if b >= 32 && b <= 127 {
-                result.append_char(b as char)
-            } else {
-                result.append_char('.');
+fn describe_field(source: &mut SliceSource, field: &Field, label: &str, depth: usize,
+                  result: &mut StringBuilder) -> bipack_source::Result<()> {
+    let offset = source.position();
+    let value = match field {
+        Field::FixedU8 => format!("FixedU8 = {}", source.get_u8()?),
+        Field::FixedU16 => format!("FixedU16 = {}", source.get_u16()?),
+        Field::FixedU32 => format!("FixedU32 = {}", source.get_u32()?),
+        Field::FixedU64 => format!("FixedU64 = {}", source.get_u64()?),
+        Field::SmartUint => format!("SmartUint = {}", source.get_unsigned()?),
+        Field::SmartInt => format!("SmartInt = {}", source.get_signed()?),
+        Field::VarBytes => {
+            let bytes = source.get_var_bytes()?;
+            let mut hex = String::new();
+            for b in &bytes { hex.push_str(&format!("{:02x}", b)); }
+            format!("VarBytes[{}] = {}", bytes.len(), hex)
+        }
+        Field::Str => format!("Str = {:?}", source.get_str()?),
+        Field::Array(item) | Field::SortedArray(item) => {
+            let count = source.get_unsigned()?;
+            let kind = if matches!(field, Field::Array(_)) { "Array" } else { "SortedArray" };
+            result.append(format!("{:04X} {:indent$}{} {}[{}]\n", offset, "", label, kind, count,
+                                  indent = depth * 2));
+            for i in 0..count {
+                describe_field(source, item, &format!("{}.{}", label, i), depth + 1, result)?;
             }
+            return Ok(());
         }
-        for i in counter..16 { result.append_char(' '); }
-        result.append("|\n");
+    };
+    result.append(format!("{:04X} {:indent$}{} {}\n", offset, "", label, value, indent = depth * 2));
+    Ok(())
+}
+
+/// Convert binary data to lowercase hex string, two digits per byte, without separators.
+pub fn to_hex(data: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut result = String::with_capacity(data.len() * 2);
+    for b in data {
+        result.push(DIGITS[(b >> 4) as usize] as char);
+        result.push(DIGITS[(b & 0x0F) as usize] as char);
     }
+    result
+}
 
-    while offset < data.len() {
-        if counter == 0 {
-            result.append(format!("{:04X} ", offset))
+/// Parse hex string as produced by [to_hex], either case is accepted. Odd length or
+/// non-hex characters cause [bipack_source::BipackError::InvalidValue].
+pub fn from_hex(text: &str) -> bipack_source::Result<Vec<u8>> {
+    fn nibble(c: u8) -> bipack_source::Result<u8> {
+        match c {
+            b'0'..=b'9' => Ok(c - b'0'),
+            b'a'..=b'f' => Ok(c - b'a' + 10),
+            b'A'..=b'F' => Ok(c - b'A' + 10),
+            _ => Err(bipack_source::BipackError::InvalidValue),
         }
-        counter += 1;
-        result.append(format!("{:02x} ", data[offset]));
-        offset += 1;
-        if counter == 16 {
-            ascii_dump(&mut result, counter, data, offset);
-            counter = 0;
+    }
+    let bytes = text.as_bytes();
+    if !bytes.len().is_multiple_of(2) { return Err(bipack_source::BipackError::InvalidValue); }
+    bytes.chunks(2).map(|pair| Ok((nibble(pair[0])? << 4) | nibble(pair[1])?)).collect()
+}
+
+/// CRC-32 (IEEE 802.3, as in zip, png, etc.) of the data. Bitwise implementation, slow but
+/// tiny, which is what we need in smart contracts.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+    for b in data {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
         }
     }
-    if counter != 0 { ascii_dump(&mut result, counter, data, offset); }
-    result.string().unwrap()
+    !crc
+}
+
+/// Zig-zag encoding of signed values: `0, -1, 1, -2, ...` map to `0, 1, 2, 3, ...`, so small
+/// values of either sign are small unsigned, which smartints and varints pack in few bytes.
+pub(crate) fn zigzag_encode(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Decode value encoded with [zigzag_encode].
+pub(crate) fn zigzag_decode(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+/// Append 4 bytes of big-endian [crc32] of the whole data, so the reader could check the
+/// message integrity before decoding it with [unseal].
+pub fn seal(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len() + 4);
+    result.extend_from_slice(data);
+    result.extend_from_slice(&crc32(data).to_be_bytes());
+    result
+}
+
+/// Check and strip the checksum appended by [seal]. Returns the original data or
+/// [bipack_source::BipackError::ChecksumMismatch] if it is corrupted.
+pub fn unseal(data: &[u8]) -> bipack_source::Result<&[u8]> {
+    if data.len() < 4 {
+        return Err(bipack_source::BipackError::NoDataError { offset: data.len(), needed: 4 - data.len() });
+    }
+    let (body, crc) = data.split_at(data.len() - 4);
+    if crc32(body).to_be_bytes() != crc {
+        return Err(bipack_source::BipackError::ChecksumMismatch);
+    }
+    Ok(body)
 }
 
+const COMPACT_DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encode packed data to URL-safe text: base64url alphabet without padding. Unlike
+/// `to_base64` it is always available, implemented here without dependencies.
+pub fn to_compact(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let mut bits = 0u32;
+        for (i, b) in chunk.iter().enumerate() { bits |= (*b as u32) << (16 - i * 8); }
+        for i in 0..chunk.len() + 1 {
+            result.push(COMPACT_DIGITS[(bits >> (18 - i * 6)) as usize & 0x3F] as char);
+        }
+    }
+    result
+}
+
+/// Decode text produced by [to_compact]. Characters out of the alphabet, padding,
+/// impossible length or non-zero unused bits cause [bipack_source::BipackError::InvalidValue].
+pub fn from_compact(text: &str) -> bipack_source::Result<Vec<u8>> {
+    fn digit(c: u8) -> bipack_source::Result<u32> {
+        match c {
+            b'A'..=b'Z' => Ok((c - b'A') as u32),
+            b'a'..=b'z' => Ok((c - b'a' + 26) as u32),
+            b'0'..=b'9' => Ok((c - b'0' + 52) as u32),
+            b'-' => Ok(62),
+            b'_' => Ok(63),
+            _ => Err(bipack_source::BipackError::InvalidValue),
+        }
+    }
+    let chars = text.as_bytes();
+    if chars.len() % 4 == 1 { return Err(bipack_source::BipackError::InvalidValue); }
+    let mut result = Vec::with_capacity(chars.len() / 4 * 3 + 2);
+    for chunk in chars.chunks(4) {
+        let mut bits = 0u32;
+        for (i, c) in chunk.iter().enumerate() { bits |= digit(*c)? << (18 - i * 6); }
+        let size = chunk.len() - 1;
+        if bits & (0xFFFFFF >> (size * 8)) != 0 { return Err(bipack_source::BipackError::InvalidValue); }
+        for i in 0..size { result.push((bits >> (16 - i * 8)) as u8); }
+    }
+    Ok(result)
+}
+
+/// Encode packed data to text, e.g. to embed it in JSON. Uses standard base64 alphabet
+/// without padding, the same as test fixtures use.
+#[cfg(feature = "base64")]
+pub fn to_base64(data: &[u8]) -> String {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD_NO_PAD.encode(data)
+}
+
+/// Decode text produced by [to_base64] back to packed data.
+#[cfg(feature = "base64")]
+pub fn from_base64(text: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD_NO_PAD.decode(text)
+}
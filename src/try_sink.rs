@@ -0,0 +1,148 @@
+// Copyright 2023 by Sergey S. Chernov.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::fmt::{Display, Formatter};
+
+use crate::bipack_sink::{BipackSink, IntoU64};
+
+/// The sink has no room for the data being written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SinkFull;
+
+impl Display for SinkFull {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "sink is full")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SinkFull {}
+
+/// Fallible counterpart of [BipackSink] for bounded buffers, where overflow must be
+/// reported, like fixed arrays on embedded targets. The encoding is the same as of
+/// [BipackSink] methods with the same names.
+///
+/// To implement, override [TryBipackSink::try_put_u8] and preferably
+/// [TryBipackSink::try_put_fixed_bytes]: default methods could write part of the data
+/// before failing, while [SliceSink] writes nothing when there is no room.
+pub trait TryBipackSink {
+    fn try_put_u8(self: &mut Self, data: u8) -> Result<(), SinkFull>;
+
+    fn try_put_fixed_bytes(self: &mut Self, data: &[u8]) -> Result<(), SinkFull> {
+        for b in data { self.try_put_u8(*b)?; }
+        Ok(())
+    }
+
+    fn try_put_var_bytes(self: &mut Self, data: &[u8]) -> Result<(), SinkFull> {
+        self.try_put_unsigned(data.len())?;
+        self.try_put_fixed_bytes(data)
+    }
+
+    fn try_put_str(self: &mut Self, str: &str) -> Result<(), SinkFull> {
+        self.try_put_var_bytes(str.as_bytes())
+    }
+
+    fn try_put_u16(self: &mut Self, value: u16) -> Result<(), SinkFull> {
+        self.try_put_fixed_bytes(&value.to_be_bytes())
+    }
+    fn try_put_u32(self: &mut Self, value: u32) -> Result<(), SinkFull> {
+        self.try_put_fixed_bytes(&value.to_be_bytes())
+    }
+    fn try_put_u64(self: &mut Self, value: u64) -> Result<(), SinkFull> {
+        self.try_put_fixed_bytes(&value.to_be_bytes())
+    }
+
+    fn try_put_i64(self: &mut Self, value: i64) -> Result<(), SinkFull> {
+        self.try_put_u64(value as u64)
+    }
+    fn try_put_i32(self: &mut Self, value: i32) -> Result<(), SinkFull> {
+        self.try_put_u32(value as u32)
+    }
+    fn try_put_i16(self: &mut Self, value: i16) -> Result<(), SinkFull> {
+        self.try_put_u16(value as u16)
+    }
+    fn try_put_i8(self: &mut Self, value: i8) -> Result<(), SinkFull> {
+        self.try_put_u8(value as u8)
+    }
+
+    fn try_put_unsigned<T: IntoU64>(self: &mut Self, number: T) -> Result<(), SinkFull> {
+        let mut buffer = StackBuffer::new();
+        buffer.put_unsigned(number);
+        self.try_put_fixed_bytes(buffer.as_slice())
+    }
+
+    fn try_put_signed(self: &mut Self, value: i64) -> Result<(), SinkFull> {
+        let mut buffer = StackBuffer::new();
+        buffer.put_signed(value);
+        self.try_put_fixed_bytes(buffer.as_slice())
+    }
+}
+
+/// Small buffer on stack enough to hold any packed number.
+struct StackBuffer {
+    data: [u8; 16],
+    size: usize,
+}
+
+impl StackBuffer {
+    fn new() -> StackBuffer { StackBuffer { data: [0; 16], size: 0 } }
+
+    fn as_slice(&self) -> &[u8] { &self.data[..self.size] }
+}
+
+impl BipackSink for StackBuffer {
+    fn put_u8(self: &mut Self, data: u8) {
+        self.data[self.size] = data;
+        self.size += 1;
+    }
+}
+
+/// Fallible sink writing to a fixed mutable slice, fails when it is full.
+pub struct SliceSink<'a> {
+    data: &'a mut [u8],
+    position: usize,
+}
+
+impl<'a> SliceSink<'a> {
+    pub fn from(data: &'a mut [u8]) -> SliceSink<'a> {
+        SliceSink { data, position: 0 }
+    }
+
+    /// Number of bytes written.
+    pub fn position(&self) -> usize { self.position }
+
+    /// The written part of the slice.
+    pub fn as_slice(&self) -> &[u8] { &self.data[..self.position] }
+}
+
+impl<'a> TryBipackSink for SliceSink<'a> {
+    fn try_put_u8(self: &mut Self, data: u8) -> Result<(), SinkFull> {
+        self.try_put_fixed_bytes(&[data])
+    }
+
+    fn try_put_fixed_bytes(self: &mut Self, data: &[u8]) -> Result<(), SinkFull> {
+        if data.len() > self.data.len() - self.position { return Err(SinkFull); }
+        self.data[self.position..self.position + data.len()].copy_from_slice(data);
+        self.position += data.len();
+        Ok(())
+    }
+
+    fn try_put_var_bytes(self: &mut Self, data: &[u8]) -> Result<(), SinkFull> {
+        let start = self.position;
+        let result = self.try_put_unsigned(data.len())
+            .and_then(|_| self.try_put_fixed_bytes(data));
+        if result.is_err() { self.position = start; }
+        result
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright 2023 by Sergey S. Chernov.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use wasm_bindgen::prelude::*;
+
+use crate::bipack_sink::BipackSink;
+use crate::bipack_source::{BipackSource, SliceSource};
+
+/// Thin facade to pack and unpack common values from JS host code, so it does not need to
+/// reimplement smartint. Binaries are `Uint8Array` on the JS side and `u64` is `BigInt`.
+/// Each call packs or unpacks a single value; decoding errors are thrown as JS `Error` with
+/// the [crate::bipack_source::BipackError] text.
+#[wasm_bindgen]
+pub struct WasmCodec;
+
+#[wasm_bindgen]
+impl WasmCodec {
+    /// Pack unsigned as smartint, see [BipackSink::put_unsigned].
+    pub fn encode_u64(value: u64) -> Vec<u8> {
+        let mut result = Vec::new();
+        result.put_unsigned(value);
+        result
+    }
+
+    /// Pack string, see [BipackSink::put_str].
+    pub fn encode_str(value: &str) -> Vec<u8> {
+        let mut result = Vec::new();
+        result.put_str(value);
+        result
+    }
+
+    /// Pack binary with its size, see [BipackSink::put_var_bytes].
+    pub fn encode_bytes(value: &[u8]) -> Vec<u8> {
+        let mut result = Vec::new();
+        result.put_var_bytes(value);
+        result
+    }
+
+    /// Unpack smartint packed with [WasmCodec::encode_u64]; the data must contain nothing else.
+    pub fn decode_u64(data: &[u8]) -> Result<u64, JsError> {
+        decode(data, |s| s.get_unsigned())
+    }
+
+    /// Unpack string packed with [WasmCodec::encode_str]; the data must contain nothing else.
+    pub fn decode_str(data: &[u8]) -> Result<String, JsError> {
+        decode(data, |s| s.get_str())
+    }
+
+    /// Unpack binary packed with [WasmCodec::encode_bytes]; the data must contain nothing else.
+    pub fn decode_bytes(data: &[u8]) -> Result<Vec<u8>, JsError> {
+        decode(data, |s| s.get_var_bytes())
+    }
+}
+
+fn decode<T>(data: &[u8], f: impl FnOnce(&mut SliceSource) -> crate::bipack_source::Result<T>)
+             -> Result<T, JsError> {
+    let mut source = SliceSource::from(data);
+    let result = f(&mut source).and_then(|value| source.finish().map(|_| value));
+    result.map_err(|e| JsError::new(&e.to_string()))
+}